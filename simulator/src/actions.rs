@@ -1,13 +1,17 @@
-use std::sync::mpsc::channel;
+use std::{collections::HashMap, sync::mpsc::channel};
 
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use once_cell::sync::Lazy;
-use queuing_system_modeling::system::System;
+use queuing_system_modeling::{
+    discipline::QueueDiscipline,
+    summary::Summary,
+    system::{self, System},
+};
 use threadpool::ThreadPool;
 
 use crate::{
     broadcaster,
-    config::{Config, Experiment},
+    config::{BulkService, Config, Experiment},
     stats::SysState,
 };
 
@@ -21,69 +25,358 @@ static PROGRESS_BAR_STYLE: Lazy<ProgressStyle> = Lazy::new(|| {
         .progress_chars(PROGRESS_BAR_CHARS)
 });
 
-///! Run simulation with given config
+/// Run simulation with given config
 pub(crate) fn run_simulation(
     name: String,
     config: Experiment,
     pb: ProgressBar,
-    _stop_rx: broadcaster::Receiver<()>,
-) {
+    stop_rx: broadcaster::Receiver<()>,
+) -> Summary {
     let Experiment {
         nodes_number,
         queue_capacity,
+        discipline,
+        preemption,
+        balking,
+        orbit,
+        batch_acceptance,
+        bulk_service,
+        vacation,
+        setup,
+        breakdowns,
+        servers,
+        assignment,
+        bucket_width,
         producer,
         seconds,
-        ..
+        seed,
+        classes,
+        populations: _,
     } = config;
 
-    let mut system = System::new(
+    let mut system = <System>::from_class(
         nodes_number,
-        queue_capacity,
-        producer.consuming_distribution.into(),
-        producer.producing_distribution.into(),
-    );
-    let mut current_time = 0.0;
+        system::QueueCapacity::from(queue_capacity),
+        producer.into(),
+        seed,
+    )
+    .with_discipline(Box::<dyn QueueDiscipline>::from(discipline))
+    .with_preemption(preemption.into())
+    .with_balking(balking)
+    .with_batch_acceptance(batch_acceptance.into())
+    .with_assignment(assignment.into());
+    if let Some(orbit) = orbit {
+        system = system.with_orbit(orbit.into());
+    }
+    if let Some(BulkService { min, max }) = bulk_service {
+        system = system.with_bulk_service(min, max);
+    }
+    if let Some(vacation) = vacation {
+        system = system.with_vacation(vacation.into());
+    }
+    if let Some(setup) = setup {
+        system = system.with_setup(setup.into());
+    }
+    if let Some(breakdowns) = breakdowns {
+        system = system.with_breakdowns(breakdowns.into());
+    }
+    if let Some(width) = bucket_width {
+        system = system.with_buckets(width);
+    }
+    if !servers.is_empty() {
+        system = system.with_servers(servers.into_iter().map(Into::into));
+    }
+    for class in classes {
+        system = system.with_class(class.into());
+    }
 
     let mut wrt = csv::Writer::from_path(format!("{}.csv", name)).unwrap();
 
-    wrt.write_record(&[
+    wrt.write_record([
         "seconds",
         "requests_in_system",
         "waiting_mean",
+        "waiting_variance",
         "reqs_in_system_mean",
+        "queue_length_mean",
+        "busy_nodes_mean",
+        "offered",
+        "lost",
+        "loss_probability",
     ])
     .unwrap();
 
     let mut last_state = SysState::default();
+    let mut stopped = false;
 
-    while current_time < seconds {
-        let state = system.next();
-        current_time = state.current_tick;
+    while system.next_event_time() <= seconds {
+        if !matches!(stop_rx.try_recv(), Ok(None)) {
+            stopped = true;
+            break;
+        }
 
-        last_state.next(
-            current_time,
-            state.requests_in_system,
-            state.finished_request,
-        );
+        let event = system
+            .next()
+            .expect("event before the horizon is scheduled");
 
-        pb.set_position(current_time as u64);
+        last_state.next(&event, system.summary());
+
+        pb.set_position(event.time as u64);
 
         wrt.write_record(last_state.to_strings()).unwrap();
     }
     pb.finish();
+
+    let summary = if stopped {
+        system.summary().clone()
+    } else {
+        system.run_until(seconds)
+    };
+
+    write_state_probabilities(&name, &summary);
+    write_classes(&name, &summary);
+    write_group_sizes(&name, &summary);
+    write_nodes(&name, &summary);
+    write_buckets(&name, &summary);
+    write_phases(&name, &summary);
+
+    summary
+}
+
+/// Write estimated probabilities of system states to a separate table.
+///
+/// Besides aggregated \(p_k\), there is a column for each class with
+/// probabilities that there are \(k\) requests of that class.
+fn write_state_probabilities(name: &str, summary: &Summary) {
+    let mut wrt = csv::Writer::from_path(format!("{}-p_k.csv", name)).unwrap();
+
+    let mut headers = vec!["k".to_string(), "p_k".to_string()];
+    headers.extend((0..summary.classes.len()).map(|class| format!("class_{}", class)));
+    wrt.write_record(headers).unwrap();
+
+    let p_k = summary.state_probabilities();
+    let classes_p_k = summary
+        .classes
+        .iter()
+        .map(Summary::state_probabilities)
+        .collect::<Vec<_>>();
+
+    for (k, p) in p_k.into_iter().enumerate() {
+        let mut record = vec![k.to_string(), p.to_string()];
+        record.extend(
+            classes_p_k
+                .iter()
+                .map(|p_k| p_k.get(k).copied().unwrap_or_default().to_string()),
+        );
+        wrt.write_record(record).unwrap();
+    }
+}
+
+/// Write statistics of each class of requests to a separate table.
+fn write_classes(name: &str, summary: &Summary) {
+    let mut wrt = csv::Writer::from_path(format!("{}-classes.csv", name)).unwrap();
+
+    wrt.write_record([
+        "class",
+        "offered",
+        "lost",
+        "loss_probability",
+        "balked",
+        "reneged",
+        "reneged_waiting_mean",
+        "orbited",
+        "retrials",
+        "mean_orbit_size",
+        "batch_size_mean",
+        "batch_waiting_mean",
+        "waiting_mean",
+        "sojourn_mean",
+        "reqs_in_system_mean",
+    ])
+    .unwrap();
+
+    for (class, summary) in summary.classes.iter().enumerate() {
+        wrt.write_record([
+            class.to_string(),
+            summary.offered.to_string(),
+            summary.lost.to_string(),
+            summary.loss_probability().to_string(),
+            summary.balked.to_string(),
+            summary.reneged.to_string(),
+            summary.reneged_waiting_mean().to_string(),
+            summary.orbited.to_string(),
+            summary.retrials.to_string(),
+            summary.mean_orbit_size().to_string(),
+            summary.batch_size_mean().to_string(),
+            summary.batch_waiting_mean().to_string(),
+            summary.waiting_mean().to_string(),
+            summary.sojourn_mean().to_string(),
+            summary.mean_in_system().to_string(),
+        ])
+        .unwrap();
+    }
+}
+
+/// Write distribution of sizes of groups taken by nodes to a separate table.
+fn write_group_sizes(name: &str, summary: &Summary) {
+    let mut wrt = csv::Writer::from_path(format!("{}-groups.csv", name)).unwrap();
+
+    wrt.write_record(["size", "groups", "fraction"]).unwrap();
+
+    let fractions = summary.group_size_distribution();
+    for (size, (groups, fraction)) in summary.group_sizes.iter().zip(fractions).enumerate() {
+        wrt.write_record([size.to_string(), groups.to_string(), fraction.to_string()])
+            .unwrap();
+    }
+}
+
+/// Write utilization, throughput and busy periods of each node to a separate
+/// table, the last row is for the whole system.
+fn write_nodes(name: &str, summary: &Summary) {
+    let mut wrt = csv::Writer::from_path(format!("{}-nodes.csv", name)).unwrap();
+
+    wrt.write_record([
+        "node",
+        "utilization",
+        "completed",
+        "throughput",
+        "busy_periods",
+        "busy_period_mean",
+        "busy_period_max",
+    ])
+    .unwrap();
+
+    let nodes = summary.nodes.iter().enumerate().map(|(node, stats)| {
+        (
+            node.to_string(),
+            stats.utilization(),
+            stats.completed,
+            stats.throughput(),
+            &stats.busy_periods,
+        )
+    });
+    let system = (
+        "system".to_string(),
+        summary.utilization(),
+        summary.completed,
+        summary.throughput(),
+        &summary.busy_periods,
+    );
+
+    for (node, utilization, completed, throughput, busy_periods) in nodes.chain([system]) {
+        wrt.write_record([
+            node,
+            utilization.to_string(),
+            completed.to_string(),
+            throughput.to_string(),
+            busy_periods.count.to_string(),
+            busy_periods.mean().to_string(),
+            busy_periods.length_max.to_string(),
+        ])
+        .unwrap();
+    }
+}
+
+/// Write statistics of intervals of time to a separate table, if they are
+/// collected.
+fn write_buckets(name: &str, summary: &Summary) {
+    if summary.bucket_width.is_none() {
+        return;
+    }
+
+    let mut wrt = csv::Writer::from_path(format!("{}-buckets.csv", name)).unwrap();
+
+    wrt.write_record([
+        "start",
+        "arrival_rate",
+        "completed",
+        "waiting_mean",
+        "sojourn_mean",
+        "queue_length_mean",
+        "busy_nodes_mean",
+    ])
+    .unwrap();
+
+    for bucket in &summary.buckets {
+        wrt.write_record([
+            bucket.start.to_string(),
+            bucket.arrival_rate().to_string(),
+            bucket.completed.to_string(),
+            bucket.waiting_mean().to_string(),
+            bucket.sojourn_mean().to_string(),
+            bucket.mean_queue_length().to_string(),
+            bucket.mean_busy_nodes().to_string(),
+        ])
+        .unwrap();
+    }
 }
 
-///! Run multiple simulation in parallel
-pub(crate) fn run_simulations(config: Config) {
+/// Write time and rate of arrivals in each phase of Markov-modulated arrival
+/// processes of classes to a separate table, if there are such classes.
+fn write_phases(name: &str, summary: &Summary) {
+    if summary
+        .classes
+        .iter()
+        .all(|class| class.phase_times.is_empty())
+    {
+        return;
+    }
+
+    let mut wrt = csv::Writer::from_path(format!("{}-phases.csv", name)).unwrap();
+
+    wrt.write_record(["class", "phase", "probability", "arrival_rate"])
+        .unwrap();
+
+    for (index, class) in summary.classes.iter().enumerate() {
+        let phases = class
+            .phase_probabilities()
+            .into_iter()
+            .zip(class.phase_rates());
+        for (phase, (probability, rate)) in phases.enumerate() {
+            wrt.write_record([
+                index.to_string(),
+                phase.to_string(),
+                probability.to_string(),
+                rate.to_string(),
+            ])
+            .unwrap();
+        }
+    }
+}
+
+/// Run multiple simulation in parallel
+pub(crate) fn run_simulations(config: Config) -> HashMap<String, Summary> {
     let (mut stop_tx, _stop_rx) = broadcaster::channel();
 
     let num_thread = num_cpus::get();
     let pool = ThreadPool::new(num_thread);
     let (tx, rx) = channel();
-    let experiments_number = config.experiments.len();
     let m = MultiProgress::new();
 
-    let mut sorted: Vec<(String, Experiment)> = config.experiments.into_iter().collect();
+    // Experiments with the population sweep are run once per population.
+    let mut sweeps = HashMap::new();
+    let mut sorted: Vec<(String, Experiment)> = Vec::new();
+    for (desc, experiment) in config.experiments {
+        if experiment.populations.is_empty() {
+            sorted.push((desc, experiment));
+            continue;
+        }
+
+        let runs = experiment
+            .populations
+            .iter()
+            .map(|population| (*population, format!("{}-N{}", desc, population)))
+            .collect::<Vec<_>>();
+        for (population, run) in &runs {
+            let mut experiment = experiment.clone();
+            experiment.producer.population = Some(*population);
+            experiment.populations.clear();
+            sorted.push((run.clone(), experiment));
+        }
+        sweeps.insert(desc, runs);
+    }
+    let experiments_number = sorted.len();
+
     sorted.sort_by(|(_, a), (_, b)| a.seconds.partial_cmp(&b.seconds).unwrap().reverse());
 
     for (i, (desc, experiment)) in sorted.into_iter().enumerate() {
@@ -95,17 +388,54 @@ pub(crate) fn run_simulations(config: Config) {
         pb.set_style(PROGRESS_BAR_STYLE.clone());
 
         pool.execute(move || {
-            tx.send(run_simulation(desc, config, pb, stop_rx))
+            let state = run_simulation(desc.clone(), config, pb, stop_rx);
+            tx.send((desc, state))
                 .expect("channel will be there waiting for the pool");
         });
     }
 
-    // ctrlc::set_handler(move || {
-    //     stop_tx.send(()).expect("Failed to send stop signal");
-    // })
-    // .expect("Error setting Ctrl-C handler");
+    // Experiments stop at the current event and write what they collected.
+    ctrlc::set_handler(move || {
+        let _ = stop_tx.send(());
+    })
+    .expect("Error setting Ctrl-C handler");
 
-    let _ = rx.iter().take(experiments_number).collect::<Vec<()>>();
+    let results: HashMap<String, Summary> = rx.iter().take(experiments_number).collect();
+
+    for (desc, runs) in &sweeps {
+        write_populations(desc, runs, &results);
+    }
+
+    results
+}
+
+/// Write throughput and response time for each population of the sweep to a
+/// separate table, one row per population.
+fn write_populations(name: &str, runs: &[(usize, String)], results: &HashMap<String, Summary>) {
+    let mut wrt = csv::Writer::from_path(format!("{}-populations.csv", name)).unwrap();
+
+    wrt.write_record([
+        "population",
+        "throughput",
+        "sojourn_mean",
+        "waiting_mean",
+        "reqs_in_system_mean",
+        "utilization",
+    ])
+    .unwrap();
+
+    for (population, run) in runs {
+        let summary = &results[run];
+        wrt.write_record([
+            population.to_string(),
+            summary.throughput().to_string(),
+            summary.sojourn_mean().to_string(),
+            summary.waiting_mean().to_string(),
+            summary.mean_in_system().to_string(),
+            summary.utilization().to_string(),
+        ])
+        .unwrap();
+    }
 }
 
 // /// Convert results to csv
@@ -147,7 +477,7 @@ pub(crate) fn run_simulations(config: Config) {
 //     Ok(())
 // }
 
-// ///! Convert distribution to csv
+// /// Convert distribution to csv
 // fn convert_dstr_to_csv(results: &Results, output: PathBuf) -> eyre::Result<()> {
 //     if results.0.is_empty() {
 //         return Err(eyre::eyre!("No results"));
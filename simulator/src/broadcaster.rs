@@ -1,12 +1,12 @@
 use std::sync::mpsc::{RecvError, TryRecvError};
 
-///! Broadcasts messsages receiver
+/// Broadcasts messsages receiver
 pub(crate) struct Receiver<T> {
     receiver: std::sync::mpsc::Receiver<T>,
 }
 
 impl<T> Receiver<T> {
-    ///! Tries to receive a message. If there is no message, returns None.
+    /// Tries to receive a message. If there is no message, returns None.
     pub(crate) fn try_recv(&self) -> Result<Option<T>, RecvError> {
         match self.receiver.try_recv() {
             Ok(message) => Ok(Some(message)),
@@ -16,26 +16,32 @@ impl<T> Receiver<T> {
     }
 }
 
-///! Broadcasts messsages sender.
+/// Broadcasts messsages sender.
 pub(crate) struct Sender<T: Clone> {
     senders: Vec<std::sync::mpsc::Sender<T>>,
 }
 
 impl<T: Clone> Sender<T> {
-    ///! Create a new receiver to which all messages will be broadcasted.
+    /// Create a new receiver to which all messages will be broadcasted.
     pub(crate) fn subscribe(&mut self) -> Receiver<T> {
         let (sender, receiver) = std::sync::mpsc::channel();
         self.senders.push(sender);
         Receiver { receiver }
     }
 
+    /// Sends the message to every receiver, even if some of them were
+    /// dropped.
     pub(crate) fn send(&self, message: T) -> Result<(), RecvError> {
-        for sender in &self.senders {
-            if sender.send(message.clone()).is_err() {
-                return Err(RecvError);
-            }
+        let failed = self
+            .senders
+            .iter()
+            .filter(|sender| sender.send(message.clone()).is_err())
+            .count();
+
+        match failed {
+            0 => Ok(()),
+            _ => Err(RecvError),
         }
-        Ok(())
     }
 }
 
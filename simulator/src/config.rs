@@ -1,36 +1,324 @@
 use config::File;
-use queuing_system_modeling::distributions;
-use std::{collections::HashMap, path::PathBuf};
+use queuing_system_modeling::{
+    class::CustomerClass,
+    discipline::{self, QueueDiscipline},
+    distributions::{self, Mmpp},
+    server, system,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub(crate) enum ConsumingDisrtibution {
+    Exponential {
+        expected: f64,
+    },
+    Degenerate {
+        expected: f64,
+    },
+    /// Sum of `phases` exponential phases with the total mean `expected`.
+    Erlang {
+        phases: u32,
+        expected: f64,
+    },
+    /// Pairs of the probability and the expected value of exponential
+    /// branches.
+    Hyperexponential {
+        branches: Vec<(f64, f64)>,
+    },
+    Uniform {
+        min: f64,
+        max: f64,
+    },
+    /// Observed values taken with equal probability.
+    Empirical {
+        values: Vec<f64>,
+    },
+}
+
+/// Number of requests arriving together.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BatchDistribution {
+    Constant { size: usize },
+    Geometric { expected: f64 },
+    Uniform { min: usize, max: usize },
+    Empirical { weights: Vec<f64> },
+}
+
+/// Either exponential times between arrivals with the given mean, arrivals
+/// with rate changing with time, or times between arrivals following any
+/// distribution of service times.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum ProducingDistribution {
     Exponential { expected: f64 },
-    Degenerate { expected: f64 },
+    NonHomogeneous(RateFunction),
+    Renewal(ConsumingDisrtibution),
+    Modulated(MarkovArrivals),
+}
+
+/// Poisson arrivals with rate depending on the phase of a hidden Markov
+/// chain.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MarkovArrivals {
+    /// Generator matrix of the chain and rates of arrivals in its phases.
+    Mmpp {
+        generator: Vec<Vec<f64>>,
+        rates: Vec<f64>,
+    },
+    /// Arrivals with `rate` during "on" periods only, periods are
+    /// exponential with expected lengths `on` and `off`.
+    OnOff { rate: f64, on: f64, off: f64 },
 }
 
+/// Rate of arrivals \(\lambda(t)\) of the non-homogeneous Poisson process.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct ProducingDistribution {
-    expected: f64,
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RateFunction {
+    /// Pairs of the start time and the rate.
+    Piecewise {
+        schedule: Vec<(f64, f64)>,
+        #[serde(default)]
+        period: Option<f64>,
+    },
+    Sinusoidal {
+        mean: f64,
+        amplitude: f64,
+        period: f64,
+        #[serde(default)]
+        phase: f64,
+    },
+    /// The same as `Piecewise`, but the schedule is read from CSV file with
+    /// `time` and `rate` columns.
+    Tabulated {
+        path: PathBuf,
+        #[serde(default)]
+        period: Option<f64>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct ProducerParams {
     pub(crate) producing_distribution: ProducingDistribution,
     pub(crate) consuming_distribution: ConsumingDisrtibution,
+    /// Priority level of requests, the lower value the higher priority.
+    #[serde(default)]
+    pub(crate) priority: u32,
+    /// Time requests agree to wait in the queue, unlimited if not set.
+    #[serde(default)]
+    pub(crate) patience: Option<ConsumingDisrtibution>,
+    /// Requests arrive one by one if not set.
+    #[serde(default)]
+    pub(crate) batch: Option<BatchDistribution>,
+    /// Number of sources, each with at most one request in the system,
+    /// infinite if not set.
+    #[serde(default)]
+    pub(crate) population: Option<usize>,
+}
+
+/// Capacity of the queue: either number of places, `"loss"` for a system
+/// without waiting room or `"unbounded"` for an infinite one.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum QueueCapacity {
+    Finite(usize),
+    Named(NamedQueueCapacity),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum NamedQueueCapacity {
+    Loss,
+    Unbounded,
+}
+
+/// Order in which waiting requests are served.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Discipline {
+    #[default]
+    Fifo,
+    Lifo,
+    Siro,
+    Sjf,
+    Ljf,
+    Priority,
+}
+
+/// What happens to the request in service when request with higher
+/// priority arrives.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Preemption {
+    #[default]
+    None,
+    Resume,
+    Repeat,
+}
+
+/// How batches are accepted when there is not enough place for all their
+/// requests.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BatchAcceptance {
+    #[default]
+    Partial,
+    Whole,
+}
+
+/// Orbit where blocked requests wait before trying again.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Orbit {
+    pub(crate) retrial_distribution: ConsumingDisrtibution,
+    /// Maximum number of repeated attempts, unlimited if not set.
+    #[serde(default)]
+    pub(crate) max_retrials: Option<u32>,
+}
+
+/// What a node does if there are no requests when it returns from
+/// vacation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum VacationPolicy {
+    Single,
+    #[default]
+    Multiple,
+}
+
+/// Which requests a node serves before its next vacation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum VacationService {
+    #[default]
+    Exhaustive,
+    Gated,
+}
+
+/// Vacations of nodes without requests to serve.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Vacation {
+    pub(crate) duration: ConsumingDisrtibution,
+    #[serde(default)]
+    pub(crate) policy: VacationPolicy,
+    #[serde(default)]
+    pub(crate) service: VacationService,
+}
+
+/// What happens to requests served by the failed node.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Interruption {
+    #[default]
+    Requeue,
+    Resume,
+    Lose,
+}
+
+/// Failures and repairs of nodes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Breakdowns {
+    pub(crate) uptime: ConsumingDisrtibution,
+    pub(crate) repair: ConsumingDisrtibution,
+    #[serde(default)]
+    pub(crate) interruption: Interruption,
+}
+
+/// Node with its own speed and, optionally, service times.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Server {
+    #[serde(default = "default_speed")]
+    pub(crate) speed: f64,
+    /// Service times of requests are used if not set.
+    #[serde(default)]
+    pub(crate) consuming_distribution: Option<ConsumingDisrtibution>,
+}
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+/// How a node is chosen among idle ones.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Assignment {
+    #[default]
+    FastestFirst,
+    Random,
+    LongestIdle,
+    RoundRobin,
+}
+
+/// Nodes take groups of `min` to `max` waiting requests and serve them
+/// together.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub(crate) struct BulkService {
+    pub(crate) min: usize,
+    pub(crate) max: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct Experiment {
     pub(crate) nodes_number: usize,
-    pub(crate) queue_capacity: usize,
+    pub(crate) queue_capacity: QueueCapacity,
+    #[serde(default)]
+    pub(crate) discipline: Discipline,
+    #[serde(default)]
+    pub(crate) preemption: Preemption,
+    /// Probabilities to join the queue of the given length, every request
+    /// joins if empty.
+    #[serde(default)]
+    pub(crate) balking: Vec<f64>,
+    /// Blocked requests are lost if there is no orbit.
+    #[serde(default)]
+    pub(crate) orbit: Option<Orbit>,
+    #[serde(default)]
+    pub(crate) batch_acceptance: BatchAcceptance,
+    /// Requests are served one by one if not set.
+    #[serde(default)]
+    pub(crate) bulk_service: Option<BulkService>,
+    /// Nodes without requests wait idle if there are no vacations.
+    #[serde(default)]
+    pub(crate) vacation: Option<Vacation>,
+    /// Idle nodes start serving immediately if not set.
+    #[serde(default)]
+    pub(crate) setup: Option<ConsumingDisrtibution>,
+    /// Nodes never fail if not set.
+    #[serde(default)]
+    pub(crate) breakdowns: Option<Breakdowns>,
+    /// Statistics are split into intervals of this length if set.
+    #[serde(default)]
+    pub(crate) bucket_width: Option<f64>,
+    /// One server per node, all nodes are equal if empty.
+    #[serde(default)]
+    pub(crate) servers: Vec<Server>,
+    #[serde(default)]
+    pub(crate) assignment: Assignment,
 
     pub(crate) seconds: f64,
 
+    /// Seed for the random number generators of the system, so the
+    /// experiment can be reproduced.
+    #[serde(default)]
+    pub(crate) seed: u64,
+
     #[serde(flatten)]
     pub(crate) producer: ProducerParams,
+
+    /// Experiment runs once for each population of sources of the first
+    /// class, instead of its `population`, if not empty.
+    #[serde(default)]
+    pub(crate) populations: Vec<usize>,
+
+    /// Classes of requests in addition to the one defined by `producer`.
+    #[serde(default)]
+    pub(crate) classes: Vec<ProducerParams>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,14 +329,132 @@ pub(crate) struct Config {
 }
 
 impl Config {
-    ///! Loads config from file
+    /// Loads config from file, together with the files it refers to
     pub(crate) fn from_file(path: PathBuf) -> eyre::Result<Self> {
-        Ok(config::Config::builder()
-            .add_source(File::from(path))
+        let mut config = config::Config::builder()
+            .add_source(File::from(path.clone()))
             .build()
             .map_err(|e| eyre::eyre!("Failed to load config: {}", e))?
             .try_deserialize::<Self>()
-            .map_err(|e| eyre::eyre!("Failed to parse config: {}", e))?)
+            .map_err(|e| eyre::eyre!("Failed to parse config: {}", e))?;
+
+        // Files the config refers to are next to it, wherever it is run from.
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for experiment in config.experiments.values_mut() {
+            experiment.check()?;
+            for producer in std::iter::once(&mut experiment.producer).chain(&mut experiment.classes)
+            {
+                match &mut producer.producing_distribution {
+                    ProducingDistribution::NonHomogeneous(rate) => rate.load(directory)?,
+                    ProducingDistribution::Modulated(process) => {
+                        process.process()?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+impl RateFunction {
+    /// Reads the schedule of `Tabulated` rates and replaces them with the
+    /// `Piecewise` ones. Relative paths are resolved against `directory`.
+    fn load(&mut self, directory: &Path) -> eyre::Result<()> {
+        let Self::Tabulated { path, period } = self else {
+            return Ok(());
+        };
+        let path = directory.join(path);
+
+        let schedule: Vec<(f64, f64)> = csv::Reader::from_path(&path)
+            .and_then(|mut rdr| rdr.deserialize().collect())
+            .map_err(|e| eyre::eyre!("Failed to read rates from {}: {}", path.display(), e))?;
+        if schedule.is_empty()
+            || schedule.iter().any(|(_, rate)| *rate < 0.0)
+            || schedule.windows(2).any(|pair| pair[0].0 > pair[1].0)
+        {
+            return Err(eyre::eyre!(
+                "Rates in {} should be non-negative and ordered by time",
+                path.display()
+            ));
+        }
+
+        *self = Self::Piecewise {
+            schedule,
+            period: *period,
+        };
+        Ok(())
+    }
+}
+
+impl Experiment {
+    /// Checks the distributions which are not checked by parsing, so the
+    /// simulation does not fail in the middle.
+    fn check(&self) -> eyre::Result<()> {
+        let producers = std::iter::once(&self.producer).chain(&self.classes);
+        let distributions = producers
+            .flat_map(|producer| {
+                let renewal = match &producer.producing_distribution {
+                    ProducingDistribution::Renewal(interval) => Some(interval),
+                    _ => None,
+                };
+                [
+                    Some(&producer.consuming_distribution),
+                    producer.patience.as_ref(),
+                    renewal,
+                ]
+            })
+            .chain([
+                self.orbit.as_ref().map(|orbit| &orbit.retrial_distribution),
+                self.vacation.as_ref().map(|vacation| &vacation.duration),
+                self.setup.as_ref(),
+                self.breakdowns
+                    .as_ref()
+                    .map(|breakdowns| &breakdowns.uptime),
+                self.breakdowns
+                    .as_ref()
+                    .map(|breakdowns| &breakdowns.repair),
+            ])
+            .chain(
+                self.servers
+                    .iter()
+                    .map(|server| server.consuming_distribution.as_ref()),
+            )
+            .flatten();
+
+        for distribution in distributions {
+            if let ConsumingDisrtibution::Empirical { values } = distribution {
+                distributions::ConsumingDistribution::empirical(values.clone())
+                    .map_err(|e| eyre::eyre!("Invalid empirical distribution: {}", e))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl MarkovArrivals {
+    /// Builds the process, failing if its parameters do not define one.
+    fn process(&self) -> eyre::Result<Mmpp> {
+        let process = match self {
+            Self::Mmpp { generator, rates } => Mmpp::try_new(generator.clone(), rates.clone()),
+            Self::OnOff { rate, on, off } => {
+                let valid = rate.is_finite() && *rate >= 0.0 && *on > 0.0 && *off > 0.0;
+                if !valid {
+                    return Err(eyre::eyre!(
+                        "Rate of on-off arrivals should be non-negative and expected lengths \
+                         of periods positive, got rate {}, on {}, off {}",
+                        rate,
+                        on,
+                        off
+                    ));
+                }
+                Ok(Mmpp::on_off(*rate, *on, *off))
+            }
+        };
+
+        process.map_err(|e| eyre::eyre!("Invalid Markov-modulated arrivals: {}", e))
     }
 }
 
@@ -57,7 +463,176 @@ impl From<ProducingDistribution> for distributions::ProducingDistribution {
         match value {
             // Exponential distribution is parametrized by λ, but we have expected value.
             // So we need to convert it to λ
-            ProducingDistribution { expected } => Self::Exponential { λ: 1.0 / expected },
+            ProducingDistribution::Exponential { expected } => {
+                Self::Exponential { λ: 1.0 / expected }
+            }
+            ProducingDistribution::NonHomogeneous(rate) => {
+                Self::NonHomogeneous { rate: rate.into() }
+            }
+            ProducingDistribution::Renewal(interval) => Self::Renewal {
+                interval: interval.into(),
+            },
+            ProducingDistribution::Modulated(process) => Self::Modulated {
+                process: process
+                    .process()
+                    .expect("Markov-modulated arrivals are checked on load"),
+            },
+        }
+    }
+}
+
+impl From<RateFunction> for distributions::RateFunction {
+    fn from(value: RateFunction) -> Self {
+        match value {
+            RateFunction::Piecewise { schedule, period } => Self::Piecewise { schedule, period },
+            RateFunction::Sinusoidal {
+                mean,
+                amplitude,
+                period,
+                phase,
+            } => Self::Sinusoidal {
+                mean,
+                amplitude,
+                period,
+                phase,
+            },
+            RateFunction::Tabulated { .. } => {
+                unreachable!("tabulated rates are loaded together with the config")
+            }
+        }
+    }
+}
+
+impl From<ProducerParams> for CustomerClass {
+    fn from(value: ProducerParams) -> Self {
+        let class = Self::new(
+            value.consuming_distribution.into(),
+            distributions::ProducingDistribution::from(value.producing_distribution),
+        )
+        .with_priority(value.priority);
+        let class = match value.batch {
+            Some(batch) => class.with_batch(batch.into()),
+            None => class,
+        };
+        let class = match value.population {
+            Some(sources) => class.with_population(sources),
+            None => class,
+        };
+
+        match value.patience {
+            Some(patience) => class.with_patience(patience.into()),
+            None => class,
+        }
+    }
+}
+
+impl From<QueueCapacity> for system::QueueCapacity {
+    fn from(value: QueueCapacity) -> Self {
+        match value {
+            QueueCapacity::Finite(capacity) => Self::Finite(capacity),
+            QueueCapacity::Named(NamedQueueCapacity::Loss) => Self::Loss,
+            QueueCapacity::Named(NamedQueueCapacity::Unbounded) => Self::Unbounded,
+        }
+    }
+}
+
+impl From<Discipline> for Box<dyn QueueDiscipline> {
+    fn from(value: Discipline) -> Self {
+        match value {
+            Discipline::Fifo => Box::<discipline::Fifo>::default(),
+            Discipline::Lifo => Box::<discipline::Lifo>::default(),
+            Discipline::Siro => Box::<discipline::Siro>::default(),
+            Discipline::Sjf => Box::<discipline::ShortestJobFirst>::default(),
+            Discipline::Ljf => Box::<discipline::LongestJobFirst>::default(),
+            Discipline::Priority => Box::<discipline::Priority>::default(),
+        }
+    }
+}
+
+impl From<Orbit> for system::Orbit {
+    fn from(value: Orbit) -> Self {
+        let orbit = Self::new(value.retrial_distribution.into());
+
+        match value.max_retrials {
+            Some(max_retrials) => orbit.with_max_retrials(max_retrials),
+            None => orbit,
+        }
+    }
+}
+
+impl From<Vacation> for system::Vacation {
+    fn from(value: Vacation) -> Self {
+        Self::new(value.duration.into())
+            .with_policy(match value.policy {
+                VacationPolicy::Single => system::VacationPolicy::Single,
+                VacationPolicy::Multiple => system::VacationPolicy::Multiple,
+            })
+            .with_service(match value.service {
+                VacationService::Exhaustive => system::VacationService::Exhaustive,
+                VacationService::Gated => system::VacationService::Gated,
+            })
+    }
+}
+
+impl From<Breakdowns> for system::Breakdowns {
+    fn from(value: Breakdowns) -> Self {
+        Self::new(value.uptime.into(), value.repair.into()).with_interruption(
+            match value.interruption {
+                Interruption::Requeue => system::Interruption::Requeue,
+                Interruption::Resume => system::Interruption::Resume,
+                Interruption::Lose => system::Interruption::Lose,
+            },
+        )
+    }
+}
+
+impl From<Server> for server::Server {
+    fn from(value: Server) -> Self {
+        let server = Self::new().with_speed(value.speed);
+        match value.consuming_distribution {
+            Some(service) => server.with_service(service.into()),
+            None => server,
+        }
+    }
+}
+
+impl From<Assignment> for server::Assignment {
+    fn from(value: Assignment) -> Self {
+        match value {
+            Assignment::FastestFirst => Self::FastestFirst,
+            Assignment::Random => Self::Random,
+            Assignment::LongestIdle => Self::LongestIdle,
+            Assignment::RoundRobin => Self::RoundRobin,
+        }
+    }
+}
+
+impl From<BatchDistribution> for distributions::BatchDistribution {
+    fn from(value: BatchDistribution) -> Self {
+        match value {
+            BatchDistribution::Constant { size } => Self::Constant { size },
+            BatchDistribution::Geometric { expected } => Self::Geometric { p: 1.0 / expected },
+            BatchDistribution::Uniform { min, max } => Self::Uniform { min, max },
+            BatchDistribution::Empirical { weights } => Self::Empirical { weights },
+        }
+    }
+}
+
+impl From<BatchAcceptance> for system::BatchAcceptance {
+    fn from(value: BatchAcceptance) -> Self {
+        match value {
+            BatchAcceptance::Partial => Self::Partial,
+            BatchAcceptance::Whole => Self::Whole,
+        }
+    }
+}
+
+impl From<Preemption> for system::Preemption {
+    fn from(value: Preemption) -> Self {
+        match value {
+            Preemption::None => Self::None,
+            Preemption::Resume => Self::Resume,
+            Preemption::Repeat => Self::Repeat,
         }
     }
 }
@@ -71,6 +646,20 @@ impl From<ConsumingDisrtibution> for distributions::ConsumingDistribution {
             ConsumingDisrtibution::Degenerate { expected } => {
                 Self::Degenerate { μ: 1.0 / expected }
             }
+            ConsumingDisrtibution::Erlang { phases, expected } => Self::Erlang {
+                k: phases,
+                λ: phases as f64 / expected,
+            },
+            ConsumingDisrtibution::Hyperexponential { branches } => Self::Hyperexponential {
+                branches: branches
+                    .into_iter()
+                    .map(|(probability, expected)| (probability, 1.0 / expected))
+                    .collect(),
+            },
+            ConsumingDisrtibution::Uniform { min, max } => Self::Uniform { min, max },
+            ConsumingDisrtibution::Empirical { values } => {
+                Self::empirical(values).expect("Empirical distributions are checked on load")
+            }
         }
     }
 }
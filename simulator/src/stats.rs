@@ -1,4 +1,4 @@
-use queuing_system_modeling::Request;
+use queuing_system_modeling::{summary::Summary, system::SimulationEvent};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -6,58 +6,44 @@ pub(crate) struct SysState {
     pub(crate) time: f64,
     pub(crate) requests_in_system: usize,
     pub(crate) reqs_in_system_mean: f64,
+    pub(crate) queue_length_mean: f64,
+    pub(crate) busy_nodes_mean: f64,
     pub(crate) waiting_mean: f64,
-
-    iterations: usize,
-    finished_requests: usize,
+    pub(crate) waiting_variance: f64,
+    pub(crate) offered: u64,
+    pub(crate) lost: u64,
+    pub(crate) loss_probability: f64,
 }
 
 impl SysState {
-    pub(crate) fn next(
-        &mut self,
-        seconds: f64,
-        requests_in_system: usize,
-        finished_request: Option<Request>,
-    ) {
-        if let Some(req) = finished_request {
-            self.waiting_mean =
-                Self::calc_waiting_mean(self.waiting_mean, req, self.finished_requests);
-            self.finished_requests += 1;
-        }
-        self.reqs_in_system_mean = Self::calc_queue_length_mean(
-            self.reqs_in_system_mean,
-            requests_in_system,
-            self.iterations,
-        );
-        self.requests_in_system = requests_in_system;
+    pub(crate) fn next(&mut self, event: &SimulationEvent, summary: &Summary) {
+        let requests_in_system = event.requests_in_system();
 
-        self.iterations += 1;
+        self.reqs_in_system_mean = summary.mean_in_system();
+        self.queue_length_mean = summary.mean_queue_length();
+        self.busy_nodes_mean = summary.mean_busy_nodes();
+        self.requests_in_system = requests_in_system;
+        self.waiting_mean = summary.waiting_mean();
+        self.waiting_variance = summary.waiting_variance();
+        self.offered = summary.offered;
+        self.lost = summary.lost;
+        self.loss_probability = summary.loss_probability();
 
-        self.time = seconds;
+        self.time = event.time;
     }
 
-    pub(crate) fn to_strings(&self) -> [String; 4] {
+    pub(crate) fn to_strings(&self) -> [String; 10] {
         [
             self.time.to_string(),
             self.requests_in_system.to_string(),
             self.waiting_mean.to_string(),
+            self.waiting_variance.to_string(),
             self.reqs_in_system_mean.to_string(),
+            self.queue_length_mean.to_string(),
+            self.busy_nodes_mean.to_string(),
+            self.offered.to_string(),
+            self.lost.to_string(),
+            self.loss_probability.to_string(),
         ]
     }
-
-    fn calc_waiting_mean(
-        last_waiting_mean: f64,
-        request: Request,
-        finished_requests_num: usize,
-    ) -> f64 {
-        let last_sum = last_waiting_mean * (finished_requests_num as f64);
-
-        let waiting_time = request.started_at.unwrap() - request.created_at.unwrap();
-
-        (last_sum + waiting_time) / ((finished_requests_num + 1) as f64)
-    }
-
-    fn calc_queue_length_mean(last: f64, current: usize, iterations: usize) -> f64 {
-        (last * (iterations as f64) + (current as f64)) / ((iterations + 1) as f64)
-    }
 }
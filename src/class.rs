@@ -0,0 +1,71 @@
+use crate::distributions::{ArrivalProcess, BatchDistribution, ConsumingDistribution};
+
+/// Type of requests which arrive to the system by their own arrival process
+/// and have their own distribution of service times.
+///
+/// Each request keeps index of its class in [`Request::class`](crate::Request::class).
+#[derive(Debug)]
+pub struct CustomerClass {
+    /// Distribution of service times of the class requests.
+    pub service: ConsumingDistribution,
+    /// Process generating arrivals of the class requests, e.g.
+    /// [`ProducingDistribution`](crate::distributions::ProducingDistribution).
+    pub arrival: Box<dyn ArrivalProcess>,
+    /// Distribution of the number of requests arriving together.
+    pub batch: BatchDistribution,
+    /// Priority level given to the class requests, the lower value the
+    /// higher priority.
+    pub priority: u32,
+    /// Distribution of times the class requests agree to wait in the queue,
+    /// `None` if they wait as long as needed.
+    pub patience: Option<ConsumingDistribution>,
+    /// Number of sources sending the class requests, `None` for infinite
+    /// population.
+    pub population: Option<usize>,
+}
+
+impl CustomerClass {
+    pub fn new(service: ConsumingDistribution, arrival: impl ArrivalProcess + 'static) -> Self {
+        Self {
+            service,
+            arrival: Box::new(arrival),
+            batch: BatchDistribution::default(),
+            priority: 0,
+            patience: None,
+            population: None,
+        }
+    }
+
+    /// Makes the class requests arrive in batches of random size.
+    pub fn with_batch(mut self, batch: BatchDistribution) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    /// Sets priority level of the class requests.
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Makes the class requests leave the queue if their service does not
+    /// start within a time sampled from `patience`. Requests which were
+    /// interrupted by preemption wait till the end.
+    pub fn with_patience(mut self, patience: ConsumingDistribution) -> Self {
+        self.patience = Some(patience);
+        self
+    }
+
+    /// Makes the class requests come from `sources` sources, each of which
+    /// has at most one request in the system. A source thinks for a time
+    /// sampled from the arrival distribution and sends a request, then
+    /// waits till it leaves the system in any way and thinks again, as in
+    /// Engset and machine-repairman models.
+    ///
+    /// Requests of such class arrive alone, the batch distribution is not
+    /// used.
+    pub fn with_population(mut self, sources: usize) -> Self {
+        self.population = Some(sources);
+        self
+    }
+}
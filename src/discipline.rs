@@ -0,0 +1,324 @@
+use std::{cmp::Ordering, collections::BinaryHeap, collections::VecDeque, fmt};
+
+use rand::{Rng, RngCore};
+
+use crate::request::Request;
+
+/// Defines the order in which waiting requests are taken by free nodes.
+pub trait QueueDiscipline: fmt::Debug {
+    /// Puts request which has to wait into the queue.
+    fn push(&mut self, request: Request);
+
+    /// Takes the next request to serve. `rng` is used by disciplines that
+    /// choose requests randomly.
+    fn pop(&mut self, rng: &mut dyn RngCore) -> Option<Request>;
+
+    /// Takes request with identifier `id` out of the queue, e.g. when it
+    /// leaves without being served.
+    fn remove(&mut self, id: u64) -> Option<Request>;
+
+    /// Number of waiting requests.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<D: QueueDiscipline + ?Sized> QueueDiscipline for Box<D> {
+    fn push(&mut self, request: Request) {
+        (**self).push(request)
+    }
+
+    fn pop(&mut self, rng: &mut dyn RngCore) -> Option<Request> {
+        (**self).pop(rng)
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Request> {
+        (**self).remove(id)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+}
+
+/// First in, first out: requests are served in order of arrival.
+#[derive(Debug, Default)]
+pub struct Fifo(VecDeque<Request>);
+
+impl QueueDiscipline for Fifo {
+    fn push(&mut self, request: Request) {
+        self.0.push_back(request);
+    }
+
+    fn pop(&mut self, _rng: &mut dyn RngCore) -> Option<Request> {
+        self.0.pop_front()
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Request> {
+        let index = self.0.iter().position(|request| request.id == id)?;
+        self.0.remove(index)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Last in, first out: the latest arrived request is served first.
+#[derive(Debug, Default)]
+pub struct Lifo(Vec<Request>);
+
+impl QueueDiscipline for Lifo {
+    fn push(&mut self, request: Request) {
+        self.0.push(request);
+    }
+
+    fn pop(&mut self, _rng: &mut dyn RngCore) -> Option<Request> {
+        self.0.pop()
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Request> {
+        let index = self.0.iter().position(|request| request.id == id)?;
+        Some(self.0.remove(index))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Service in random order: each waiting request is taken with equal
+/// probability.
+#[derive(Debug, Default)]
+pub struct Siro(Vec<Request>);
+
+impl QueueDiscipline for Siro {
+    fn push(&mut self, request: Request) {
+        self.0.push(request);
+    }
+
+    fn pop(&mut self, rng: &mut dyn RngCore) -> Option<Request> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let index = rng.gen_range(0..self.0.len());
+
+        Some(self.0.swap_remove(index))
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Request> {
+        let index = self.0.iter().position(|request| request.id == id)?;
+        Some(self.0.swap_remove(index))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Request with the smallest [`Request::time_to_finish`] is served first.
+/// Requests with equal time are served in order of arrival.
+#[derive(Debug, Default)]
+pub struct ShortestJobFirst(Jobs);
+
+impl QueueDiscipline for ShortestJobFirst {
+    fn push(&mut self, request: Request) {
+        self.0.push(-request.time_to_finish, request);
+    }
+
+    fn pop(&mut self, _rng: &mut dyn RngCore) -> Option<Request> {
+        self.0.pop()
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Request> {
+        self.0.remove(id)
+    }
+
+    fn len(&self) -> usize {
+        self.0.heap.len()
+    }
+}
+
+/// Request with the largest [`Request::time_to_finish`] is served first.
+/// Requests with equal time are served in order of arrival.
+#[derive(Debug, Default)]
+pub struct LongestJobFirst(Jobs);
+
+impl QueueDiscipline for LongestJobFirst {
+    fn push(&mut self, request: Request) {
+        self.0.push(request.time_to_finish, request);
+    }
+
+    fn pop(&mut self, _rng: &mut dyn RngCore) -> Option<Request> {
+        self.0.pop()
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Request> {
+        self.0.remove(id)
+    }
+
+    fn len(&self) -> usize {
+        self.0.heap.len()
+    }
+}
+
+/// Request with the highest priority, i.e. the lowest
+/// [`Request::priority`] value, is served first. Requests with equal
+/// priority are served in order of arrival.
+#[derive(Debug, Default)]
+pub struct Priority(Jobs);
+
+impl QueueDiscipline for Priority {
+    fn push(&mut self, request: Request) {
+        self.0.push(-f64::from(request.priority), request);
+    }
+
+    fn pop(&mut self, _rng: &mut dyn RngCore) -> Option<Request> {
+        self.0.pop()
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Request> {
+        self.0.remove(id)
+    }
+
+    fn len(&self) -> usize {
+        self.0.heap.len()
+    }
+}
+
+/// Requests ordered by key, the one with the greatest key is taken first.
+#[derive(Debug, Default)]
+struct Jobs {
+    heap: BinaryHeap<Job>,
+    /// Number of pushed requests, used to keep order of arrival for equal keys.
+    pushed: u64,
+}
+
+impl Jobs {
+    fn push(&mut self, key: f64, request: Request) {
+        self.heap.push(Job {
+            key,
+            order: self.pushed,
+            request,
+        });
+        self.pushed += 1;
+    }
+
+    fn pop(&mut self) -> Option<Request> {
+        self.heap.pop().map(|job| job.request)
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Request> {
+        let mut removed = None;
+
+        self.heap.retain(|job| {
+            if job.request.id == id {
+                removed = Some(job.request);
+                return false;
+            }
+            true
+        });
+
+        removed
+    }
+}
+
+#[derive(Debug)]
+struct Job {
+    key: f64,
+    order: u64,
+    request: Request,
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Job {}
+
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .total_cmp(&other.key)
+            .then_with(|| other.order.cmp(&self.order))
+    }
+}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn requests() -> Vec<Request> {
+        [(3.0, 1), (1.0, 2), (2.0, 0), (1.0, 1)]
+            .into_iter()
+            .enumerate()
+            .map(|(id, (time, priority))| Request {
+                priority,
+                ..Request::with_id(id as u64, time)
+            })
+            .collect()
+    }
+
+    fn served(mut discipline: impl QueueDiscipline) -> Vec<u64> {
+        let mut rng = StdRng::seed_from_u64(0);
+        requests()
+            .into_iter()
+            .for_each(|request| discipline.push(request));
+
+        std::iter::from_fn(|| discipline.pop(&mut rng))
+            .map(|request| request.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_disciplines_order() {
+        assert_eq!(served(Fifo::default()), vec![0, 1, 2, 3]);
+        assert_eq!(served(Lifo::default()), vec![3, 2, 1, 0]);
+        assert_eq!(served(ShortestJobFirst::default()), vec![1, 3, 2, 0]);
+        assert_eq!(served(LongestJobFirst::default()), vec![0, 2, 1, 3]);
+        assert_eq!(served(Priority::default()), vec![2, 0, 3, 1]);
+
+        let mut siro = served(Siro::default());
+        siro.sort();
+        assert_eq!(siro, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_disciplines_remove() {
+        fn remaining(mut discipline: impl QueueDiscipline) -> Vec<u64> {
+            let mut rng = StdRng::seed_from_u64(0);
+            requests()
+                .into_iter()
+                .for_each(|request| discipline.push(request));
+
+            assert_eq!(discipline.remove(2).map(|request| request.id), Some(2));
+            assert_eq!(discipline.remove(2), None);
+            assert_eq!(discipline.len(), 3);
+
+            std::iter::from_fn(|| discipline.pop(&mut rng))
+                .map(|request| request.id)
+                .collect()
+        }
+
+        assert_eq!(remaining(Fifo::default()), vec![0, 1, 3]);
+        assert_eq!(remaining(Lifo::default()), vec![3, 1, 0]);
+        assert_eq!(remaining(ShortestJobFirst::default()), vec![1, 3, 0]);
+        assert_eq!(remaining(Priority::default()), vec![0, 3, 1]);
+
+        let mut siro = remaining(Siro::default());
+        siro.sort();
+        assert_eq!(siro, vec![0, 1, 3]);
+    }
+}
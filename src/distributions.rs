@@ -1,61 +1,729 @@
-use rand_distr::{Distribution, Exp};
+use std::fmt;
 
-///! The type that defines what type of distribution for generating time of
-///! consuming for each [`Request`] will be used.
+use rand::{distributions::WeightedIndex, Rng, RngCore};
+use rand_distr::{Distribution as _, Exp};
+
+use crate::linear;
+
+/// Distribution of non-negative times, e.g. of service or between arrivals.
+///
+/// Implement it to use own distribution in the
+/// [`System`](crate::system::System) with [`ConsumingDistribution::custom`].
+pub trait TimeDistribution: fmt::Debug {
+    /// Samples the next time. All randomness should come from `rng`, so the
+    /// simulation can be reproduced.
+    fn sample(&self, rng: &mut dyn RngCore) -> f64;
+
+    /// Expected time.
+    fn mean(&self) -> f64;
+
+    /// Variance of time.
+    fn variance(&self) -> f64;
+
+    /// Short human-readable description, e.g. `Exp(λ = 0.5)`.
+    fn description(&self) -> String;
+}
+
+impl<D: TimeDistribution + ?Sized> TimeDistribution for Box<D> {
+    fn sample(&self, rng: &mut dyn RngCore) -> f64 {
+        (**self).sample(rng)
+    }
+
+    fn mean(&self) -> f64 {
+        (**self).mean()
+    }
+
+    fn variance(&self) -> f64 {
+        (**self).variance()
+    }
+
+    fn description(&self) -> String {
+        (**self).description()
+    }
+}
+
+/// Parameters of a distribution or an arrival process which do not define
+/// one, e.g. negative rates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterError(pub String);
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParameterError {}
+
+/// The type that defines what type of distribution for generating time of
+/// consuming for each [`Request`] will be used.
 #[derive(Debug)]
 pub enum ConsumingDistribution {
-    ///! Time of consuming is defined by exponential distribution.
+    /// Time of consuming is defined by exponential distribution.
     Exponential {
-        ///! The parameter of \(G(x) = 1 - e^{-\lambda x}\) distribution.
+        /// The parameter of \(G(x) = 1 - e^{-\lambda x}\) distribution.
         λ: f64,
     },
-    ///! Time of consuming is defined by constant.
+    /// Time of consuming is defined by constant.
     Degenerate {
-        ///! The parameter of \(G(x) = \frac{1}{\mu} = const\) distribution.
+        /// The parameter of \(G(x) = \frac{1}{\mu} = const\) distribution.
         μ: f64,
     },
+    /// Time is the sum of `k` exponential phases, each with rate `λ`.
+    Erlang { k: u32, λ: f64 },
+    /// Time is exponential with rate `branches[i].1` with probability
+    /// `branches[i].0`.
+    Hyperexponential {
+        /// Pairs of the probability and the rate, probabilities sum to 1.
+        branches: Vec<(f64, f64)>,
+    },
+    /// Time is equally likely any value between `min` and `max`.
+    Uniform { min: f64, max: f64 },
+    /// Time is one of the observed `values` with equal probability, see
+    /// [`ConsumingDistribution::empirical`].
+    Empirical { values: Vec<f64> },
+    /// Time follows distribution defined outside of the crate.
+    Custom(Box<dyn TimeDistribution>),
 }
 
 impl ConsumingDistribution {
-    pub(crate) fn sample(&self, rng: &mut impl rand::Rng) -> f64 {
+    /// Time is one of the observed `values`, failing if there are none or
+    /// some of them are negative or not finite.
+    pub fn empirical(values: Vec<f64>) -> Result<Self, ParameterError> {
+        if values.is_empty() {
+            return Err(ParameterError(
+                "Empirical distribution should have at least one value".to_string(),
+            ));
+        }
+        if !values
+            .iter()
+            .all(|value| value.is_finite() && *value >= 0.0)
+        {
+            return Err(ParameterError(format!(
+                "Values of empirical distribution should be non-negative, got {:?}",
+                values
+            )));
+        }
+
+        Ok(Self::Empirical { values })
+    }
+
+    /// Wraps own implementation of [`TimeDistribution`].
+    pub fn custom(distribution: impl TimeDistribution + 'static) -> Self {
+        Self::Custom(Box::new(distribution))
+    }
+}
+
+impl TimeDistribution for ConsumingDistribution {
+    fn sample(&self, rng: &mut dyn RngCore) -> f64 {
         match self {
             ConsumingDistribution::Exponential { λ } => {
                 let exp = Exp::new(*λ).unwrap();
                 exp.sample(rng)
             }
             ConsumingDistribution::Degenerate { μ } => 1.0 / μ,
+            ConsumingDistribution::Erlang { k, λ } => {
+                let gamma = rand_distr::Gamma::new(*k as f64, 1.0 / λ).unwrap();
+                gamma.sample(rng)
+            }
+            ConsumingDistribution::Hyperexponential { branches } => {
+                let index = WeightedIndex::new(branches.iter().map(|(probability, _)| probability))
+                    .unwrap()
+                    .sample(rng);
+                let exp = Exp::new(branches[index].1).unwrap();
+                exp.sample(rng)
+            }
+            ConsumingDistribution::Uniform { min, max } => rng.gen_range(*min..=*max),
+            ConsumingDistribution::Empirical { values } => values[rng.gen_range(0..values.len())],
+            ConsumingDistribution::Custom(distribution) => distribution.sample(rng),
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        match self {
+            Self::Exponential { λ } => 1.0 / λ,
+            Self::Degenerate { μ } => 1.0 / μ,
+            Self::Erlang { k, λ } => *k as f64 / λ,
+            Self::Hyperexponential { branches } => {
+                let total = branches
+                    .iter()
+                    .map(|(probability, _)| probability)
+                    .sum::<f64>();
+                let sum = branches
+                    .iter()
+                    .map(|(probability, λ)| probability / λ)
+                    .sum::<f64>();
+
+                sum / total
+            }
+            Self::Uniform { min, max } => (min + max) / 2.0,
+            Self::Empirical { values } => values.iter().sum::<f64>() / values.len() as f64,
+            Self::Custom(distribution) => distribution.mean(),
+        }
+    }
+
+    fn variance(&self) -> f64 {
+        match self {
+            Self::Exponential { λ } => 1.0 / (λ * λ),
+            Self::Degenerate { .. } => 0.0,
+            Self::Erlang { k, λ } => *k as f64 / (λ * λ),
+            Self::Hyperexponential { branches } => {
+                let total = branches
+                    .iter()
+                    .map(|(probability, _)| probability)
+                    .sum::<f64>();
+                let second_moment = branches
+                    .iter()
+                    .map(|(probability, λ)| 2.0 * probability / (λ * λ))
+                    .sum::<f64>()
+                    / total;
+
+                second_moment - self.mean().powi(2)
+            }
+            Self::Uniform { min, max } => (max - min).powi(2) / 12.0,
+            Self::Empirical { values } => {
+                let mean = self.mean();
+                values
+                    .iter()
+                    .map(|value| (value - mean).powi(2))
+                    .sum::<f64>()
+                    / values.len() as f64
+            }
+            Self::Custom(distribution) => distribution.variance(),
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            Self::Exponential { λ } => format!("Exp(λ = {})", λ),
+            Self::Degenerate { μ } => format!("D({})", 1.0 / μ),
+            Self::Erlang { k, λ } => format!("Erlang(k = {}, λ = {})", k, λ),
+            Self::Hyperexponential { branches } => format!("H{}({:?})", branches.len(), branches),
+            Self::Uniform { min, max } => format!("U({}, {})", min, max),
+            Self::Empirical { values } => format!("Empirical({} values)", values.len()),
+            Self::Custom(distribution) => distribution.description(),
         }
     }
 }
 
-///! The type that defines what type of distribution for generating time of new
-///! [`Request`] will be used.
+/// What happens next in an arrival process, sampled by
+/// [`ArrivalProcess::sample`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NextArrival {
+    /// Request arrives after the given time.
+    Arrival(f64),
+    /// Process moves to another phase after the given time, before the next
+    /// arrival.
+    PhaseChange(f64),
+    /// No more requests arrive.
+    Never,
+}
+
+/// Process generating arrivals of requests.
+///
+/// Implement it to use own arrival process in the
+/// [`System`](crate::system::System), e.g. with
+/// [`CustomerClass::new`](crate::class::CustomerClass::new). Processes whose
+/// arrivals depend on a hidden phase, like [`Mmpp`], report changes of the
+/// phase, and the system keeps the phase and its statistics.
+pub trait ArrivalProcess: fmt::Debug {
+    /// Samples what happens next after the arrival or the change of the
+    /// phase at `time`, while the process is in `phase`. All randomness
+    /// should come from `rng`, so the simulation can be reproduced.
+    fn sample(&self, time: f64, phase: usize, rng: &mut dyn RngCore) -> NextArrival;
+
+    /// Number of phases of the process, 1 if arrivals do not depend on a
+    /// phase.
+    fn phases(&self) -> usize {
+        1
+    }
+
+    /// Samples the phase in which the process starts.
+    fn initial_phase(&self, _rng: &mut dyn RngCore) -> usize {
+        0
+    }
+
+    /// Samples the phase to which the process moves from `phase` after
+    /// [`NextArrival::PhaseChange`].
+    fn jump(&self, phase: usize, _rng: &mut dyn RngCore) -> usize {
+        phase
+    }
+
+    /// Mean time between arrivals in the long run, the inverse of the
+    /// long-run rate of arrivals. Infinity if requests stop arriving.
+    fn mean(&self) -> f64;
+
+    /// Variance of times between arrivals if they are independent and
+    /// identically distributed, `None` if they depend on time or on the
+    /// phase of the process.
+    fn variance(&self) -> Option<f64>;
+
+    /// Short human-readable name of the arrival process with parameters.
+    fn description(&self) -> String;
+}
+
+impl<P: ArrivalProcess + ?Sized> ArrivalProcess for Box<P> {
+    fn sample(&self, time: f64, phase: usize, rng: &mut dyn RngCore) -> NextArrival {
+        (**self).sample(time, phase, rng)
+    }
+
+    fn phases(&self) -> usize {
+        (**self).phases()
+    }
+
+    fn initial_phase(&self, rng: &mut dyn RngCore) -> usize {
+        (**self).initial_phase(rng)
+    }
+
+    fn jump(&self, phase: usize, rng: &mut dyn RngCore) -> usize {
+        (**self).jump(phase, rng)
+    }
+
+    fn mean(&self) -> f64 {
+        (**self).mean()
+    }
+
+    fn variance(&self) -> Option<f64> {
+        (**self).variance()
+    }
+
+    fn description(&self) -> String {
+        (**self).description()
+    }
+}
+
+/// The type that defines what type of distribution for generating time of new
+/// [`Request`] will be used.
 #[derive(Debug)]
 pub enum ProducingDistribution {
-    ///! Time of producing is defined by exponential distribution.
+    /// Time of producing is defined by exponential distribution.
     Exponential {
-        ///! The parameter of \(G(x) = \lambda * e^{-\lambda x}\) distribution.
+        /// The parameter of \(G(x) = \lambda * e^{-\lambda x}\) distribution.
         λ: f64,
     },
-    ///! Time of producing is defined by constant. Used for testing.
+    /// Time of producing is defined by constant. Used for testing.
     Degenerate {
-        ///! The next sample is fixed value
+        /// The next sample is fixed value
         value: u64,
     },
+    /// Times between arrivals are independent and follow `interval`, which
+    /// makes the arrival process a general renewal one (GI).
+    Renewal {
+        /// Distribution of times between arrivals.
+        interval: ConsumingDistribution,
+    },
+    /// Requests arrive by Markov-modulated Poisson process, whose phase is
+    /// kept by the [`System`](crate::system::System).
+    Modulated {
+        /// Phases of the process and rates of arrivals in them.
+        process: Mmpp,
+    },
+    /// Requests arrive by non-homogeneous Poisson process, times are
+    /// generated by thinning of the process with the maximum rate. Requests
+    /// stop arriving if the rate stays zero from some time on.
+    NonHomogeneous {
+        /// Rate of arrivals \(\lambda(t)\) at each moment.
+        rate: RateFunction,
+    },
 }
 
-impl ProducingDistribution {
-    pub(crate) fn sample(&self, rng: &mut impl rand::Rng) -> f64 {
-        match self {
+impl ArrivalProcess for ProducingDistribution {
+    fn sample(&self, time: f64, phase: usize, rng: &mut dyn RngCore) -> NextArrival {
+        let delay = match self {
             Self::Exponential { λ } => {
                 let exp = rand_distr::Exp::new(*λ).unwrap();
                 exp.sample(rng)
             }
             Self::Degenerate { value } => *value as f64,
+            Self::Renewal { interval } => interval.sample(rng),
+            Self::Modulated { process } => return process.sample(time, phase, rng),
+            Self::NonHomogeneous { rate } => {
+                let mut next = time;
+                loop {
+                    let max = rate.max_after(next);
+                    if max <= 0.0 {
+                        return NextArrival::Never;
+                    }
+
+                    next += rand_distr::Exp::new(max).unwrap().sample(rng);
+                    if rng.gen::<f64>() * max < rate.at(next) {
+                        break next - time;
+                    }
+                }
+            }
+        };
+
+        NextArrival::Arrival(delay)
+    }
+
+    fn phases(&self) -> usize {
+        match self {
+            Self::Modulated { process } => process.phases(),
+            _ => 1,
+        }
+    }
+
+    fn initial_phase(&self, rng: &mut dyn RngCore) -> usize {
+        match self {
+            Self::Modulated { process } => process.initial_phase(rng),
+            _ => 0,
+        }
+    }
+
+    fn jump(&self, phase: usize, rng: &mut dyn RngCore) -> usize {
+        match self {
+            Self::Modulated { process } => process.jump(phase, rng),
+            _ => phase,
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        match self {
+            Self::Exponential { λ } => 1.0 / λ,
+            Self::Degenerate { value } => *value as f64,
+            Self::Renewal { interval } => interval.mean(),
+            Self::Modulated { process } => process.mean(),
+            Self::NonHomogeneous { rate } => 1.0 / rate.mean(),
+        }
+    }
+
+    fn variance(&self) -> Option<f64> {
+        match self {
+            Self::Exponential { λ } => Some(1.0 / (λ * λ)),
+            Self::Degenerate { .. } => Some(0.0),
+            Self::Renewal { interval } => Some(interval.variance()),
+            Self::Modulated { .. } | Self::NonHomogeneous { .. } => None,
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            Self::Exponential { λ } => format!("Poisson(λ = {})", λ),
+            Self::Degenerate { value } => format!("D({})", value),
+            Self::Renewal { interval } => format!("GI({})", interval.description()),
+            Self::Modulated { process } => process.description(),
+            Self::NonHomogeneous { rate } => format!("NHPP(mean λ = {})", rate.mean()),
         }
     }
 }
 
+/// Markov-modulated Poisson process: requests arrive with rate `rates[i]`
+/// while a hidden continuous-time Markov chain with `generator` matrix is in
+/// phase `i`.
+#[derive(Debug, Clone)]
+pub struct Mmpp {
+    /// Generator matrix \(Q\) of the chain: `generator[i][j]` is the rate of
+    /// moving from phase `i` to phase `j`, and each row sums to zero.
+    pub generator: Vec<Vec<f64>>,
+    /// Rates of arrivals in each phase.
+    pub rates: Vec<f64>,
+}
+
+impl Mmpp {
+    /// # Panics
+    ///
+    /// Panics if the parameters are invalid, see [`Mmpp::try_new`].
+    pub fn new(generator: Vec<Vec<f64>>, rates: Vec<f64>) -> Self {
+        Self::try_new(generator, rates).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Fails if `generator` is not a square matrix with a row per rate, if
+    /// rates of moving between phases or rates of arrivals are negative, or
+    /// if rows of `generator` do not sum to zero.
+    pub fn try_new(generator: Vec<Vec<f64>>, rates: Vec<f64>) -> Result<Self, ParameterError> {
+        if rates.is_empty()
+            || generator.len() != rates.len()
+            || generator.iter().any(|row| row.len() != rates.len())
+        {
+            return Err(ParameterError(
+                "Generator should be a square matrix with a row per phase".to_string(),
+            ));
+        }
+        if !rates.iter().all(|rate| *rate >= 0.0 && rate.is_finite()) {
+            return Err(ParameterError(format!(
+                "Rates of arrivals should be non-negative, got {:?}",
+                rates
+            )));
+        }
+        for (i, row) in generator.iter().enumerate() {
+            let valid = row
+                .iter()
+                .enumerate()
+                .all(|(j, rate)| rate.is_finite() && (i == j || *rate >= 0.0))
+                && row.iter().sum::<f64>().abs() < 1e-9;
+            if !valid {
+                return Err(ParameterError(format!(
+                    "Rows of generator should have non-negative rates sum to zero, got {:?}",
+                    row
+                )));
+            }
+        }
+
+        Ok(Self { generator, rates })
+    }
+
+    /// Interrupted Poisson process: requests arrive with `rate` during "on"
+    /// periods and do not arrive during "off" ones. Lengths of the periods
+    /// are exponential with means `on` and `off`. Phase 0 is "on".
+    pub fn on_off(rate: f64, on: f64, off: f64) -> Self {
+        Self::new(
+            vec![vec![-1.0 / on, 1.0 / on], vec![1.0 / off, -1.0 / off]],
+            vec![rate, 0.0],
+        )
+    }
+
+    /// Stationary probabilities of phases, solution of \(\pi Q = 0\) with
+    /// \(\sum_i \pi_i = 1\).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chain has no single stationary distribution, i.e. it
+    /// has several groups of phases which it never leaves.
+    pub fn stationary(&self) -> Vec<f64> {
+        let n = self.phases();
+
+        // Augmented matrix of Qᵀ π = 0 with the last equation replaced by
+        // the sum of probabilities, as the equations are dependent.
+        let matrix = (0..n)
+            .map(|j| {
+                if j == n - 1 {
+                    return vec![1.0; n + 1];
+                }
+                let mut row = (0..n).map(|i| self.generator[i][j]).collect::<Vec<_>>();
+                row.push(0.0);
+                row
+            })
+            .collect::<Vec<_>>();
+
+        linear::solve(matrix).expect("Chain of phases should have a single stationary distribution")
+    }
+
+    /// Long-run rate of arrivals, \(\sum_i \pi_i \lambda_i\).
+    pub fn mean_rate(&self) -> f64 {
+        self.stationary()
+            .iter()
+            .zip(&self.rates)
+            .map(|(probability, rate)| probability * rate)
+            .sum()
+    }
+}
+
+impl ArrivalProcess for Mmpp {
+    /// Samples time till the next arrival or change of `phase`, whichever
+    /// happens first.
+    fn sample(&self, _time: f64, phase: usize, rng: &mut dyn RngCore) -> NextArrival {
+        let arrival = self.rates[phase];
+        let total = arrival - self.generator[phase][phase];
+        if total <= 0.0 {
+            return NextArrival::Never;
+        }
+
+        let delay = Exp::new(total).unwrap().sample(rng);
+        if rng.gen::<f64>() * total < arrival {
+            NextArrival::Arrival(delay)
+        } else {
+            NextArrival::PhaseChange(delay)
+        }
+    }
+
+    fn phases(&self) -> usize {
+        self.rates.len()
+    }
+
+    /// The process starts in the stationary regime.
+    fn initial_phase(&self, rng: &mut dyn RngCore) -> usize {
+        WeightedIndex::new(self.stationary()).unwrap().sample(rng)
+    }
+
+    fn jump(&self, phase: usize, rng: &mut dyn RngCore) -> usize {
+        let weights = self.generator[phase]
+            .iter()
+            .enumerate()
+            .map(|(next, rate)| if next == phase { 0.0 } else { *rate });
+        WeightedIndex::new(weights).unwrap().sample(rng)
+    }
+
+    fn mean(&self) -> f64 {
+        1.0 / self.mean_rate()
+    }
+
+    fn variance(&self) -> Option<f64> {
+        None
+    }
+
+    fn description(&self) -> String {
+        format!("MMPP({} phases)", self.phases())
+    }
+}
+
+/// Rate of arrivals changing with time, \(\lambda(t)\).
+#[derive(Debug, Clone)]
+pub enum RateFunction {
+    /// Rate is constant between the given times: `schedule[i]` is the time
+    /// from which rate is `rates[i]`, till the next time. Rate before the
+    /// first time is zero.
+    Piecewise {
+        /// Pairs of the start time and the rate, ordered by time.
+        schedule: Vec<(f64, f64)>,
+        /// Schedule repeats every `period` if set, e.g. a day.
+        period: Option<f64>,
+    },
+    /// \(\lambda(t) = mean + amplitude \cdot \sin(2 \pi t / period +
+    /// phase)\).
+    Sinusoidal {
+        mean: f64,
+        /// Should not exceed `mean`, so the rate is not negative.
+        amplitude: f64,
+        period: f64,
+        phase: f64,
+    },
+}
+
+impl RateFunction {
+    /// Rate of arrivals at `time`.
+    pub fn at(&self, time: f64) -> f64 {
+        match self {
+            Self::Piecewise { schedule, period } => {
+                let time = period.map_or(time, |period| time.rem_euclid(period));
+                schedule
+                    .iter()
+                    .take_while(|(start, _)| *start <= time)
+                    .last()
+                    .map_or(0.0, |(_, rate)| *rate)
+            }
+            Self::Sinusoidal {
+                mean,
+                amplitude,
+                period,
+                phase,
+            } => {
+                let angle = 2.0 * std::f64::consts::PI * time / period + phase;
+                (mean + amplitude * angle.sin()).max(0.0)
+            }
+        }
+    }
+
+    /// Maximum rate, used to generate arrivals by thinning.
+    pub fn max(&self) -> f64 {
+        match self {
+            Self::Piecewise { schedule, .. } => {
+                schedule.iter().map(|(_, rate)| *rate).fold(0.0, f64::max)
+            }
+            Self::Sinusoidal {
+                mean, amplitude, ..
+            } => mean + amplitude.abs(),
+        }
+    }
+
+    /// Average rate in the long run: over a period if the rate is periodic,
+    /// otherwise the rate after the last change.
+    pub fn mean(&self) -> f64 {
+        match self {
+            Self::Piecewise {
+                schedule,
+                period: Some(period),
+            } => {
+                let ends = schedule.iter().skip(1).map(|(start, _)| *start);
+                schedule
+                    .iter()
+                    .zip(ends.chain([*period]))
+                    .map(|((start, rate), end)| {
+                        rate * (end.clamp(0.0, *period) - start.clamp(0.0, *period))
+                    })
+                    .sum::<f64>()
+                    / period
+            }
+            Self::Piecewise {
+                schedule,
+                period: None,
+            } => schedule.last().map_or(0.0, |(_, rate)| *rate),
+            Self::Sinusoidal { mean, .. } => *mean,
+        }
+    }
+
+    /// Maximum rate from `time` on, zero if no more requests arrive.
+    pub fn max_after(&self, time: f64) -> f64 {
+        match self {
+            Self::Piecewise {
+                schedule,
+                period: None,
+            } => schedule
+                .iter()
+                .filter(|(start, _)| *start > time)
+                .map(|(_, rate)| *rate)
+                .fold(self.at(time), f64::max),
+            _ => self.max(),
+        }
+    }
+}
+
+/// Distribution of the number of requests which arrive together with one
+/// arrival event.
+#[derive(Debug)]
+pub enum BatchDistribution {
+    /// Every batch has the same size.
+    Constant {
+        /// Number of requests in a batch.
+        size: usize,
+    },
+    /// Size of a batch is \(k\) with probability \((1 - p)^{k - 1} p\),
+    /// \(k \ge 1\).
+    Geometric {
+        /// Probability that a batch ends after each request.
+        p: f64,
+    },
+    /// Size of a batch is equally likely any number between `min` and `max`,
+    /// inclusive.
+    Uniform { min: usize, max: usize },
+    /// Size of a batch is \(k + 1\) with probability proportional to
+    /// `weights[k]`.
+    Empirical { weights: Vec<f64> },
+}
+
+impl BatchDistribution {
+    /// Samples size of the next batch.
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        match self {
+            Self::Constant { size } => *size,
+            Self::Geometric { p } => {
+                let geometric = rand_distr::Geometric::new(*p).unwrap();
+                geometric.sample(rng) as usize + 1
+            }
+            Self::Uniform { min, max } => rng.gen_range(*min..=*max),
+            Self::Empirical { weights } => {
+                let index = WeightedIndex::new(weights).unwrap();
+                index.sample(rng) + 1
+            }
+        }
+    }
+
+    /// Expected size of a batch.
+    pub fn mean(&self) -> f64 {
+        match self {
+            Self::Constant { size } => *size as f64,
+            Self::Geometric { p } => 1.0 / p,
+            Self::Uniform { min, max } => (min + max) as f64 / 2.0,
+            Self::Empirical { weights } => {
+                let total = weights.iter().sum::<f64>();
+                let sum = weights
+                    .iter()
+                    .enumerate()
+                    .map(|(k, weight)| (k + 1) as f64 * weight)
+                    .sum::<f64>();
+
+                sum / total
+            }
+        }
+    }
+}
+
+impl Default for BatchDistribution {
+    /// Requests arrive one by one.
+    fn default() -> Self {
+        Self::Constant { size: 1 }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -82,4 +750,207 @@ mod tests {
             expected
         );
     }
+
+    #[test]
+    fn test_consuming_distributions() {
+        let mut rng = rand::thread_rng();
+        let samples_number = 10_000;
+
+        for dstr in [
+            ConsumingDistribution::Erlang { k: 3, λ: 2.0 },
+            ConsumingDistribution::Hyperexponential {
+                branches: vec![(0.2, 0.5), (0.8, 4.0)],
+            },
+            ConsumingDistribution::Uniform { min: 1.0, max: 2.0 },
+            ConsumingDistribution::Empirical {
+                values: vec![0.5, 1.0, 3.0],
+            },
+        ] {
+            let samples = (0..samples_number)
+                .map(|_| dstr.sample(&mut rng))
+                .collect::<Vec<_>>();
+            let avg = samples.iter().sum::<f64>() / samples_number as f64;
+            assert!(
+                (avg - dstr.mean()).abs() < 0.1,
+                "average should be nearly equal to mean of {:?}, avg = {}",
+                dstr,
+                avg
+            );
+
+            let variance =
+                samples.iter().map(|x| (x - avg).powi(2)).sum::<f64>() / samples_number as f64;
+            assert!(
+                (variance - dstr.variance()).abs() < 0.3 * dstr.variance(),
+                "variance should be nearly equal to variance of {}, variance = {}",
+                dstr.description(),
+                variance
+            );
+        }
+    }
+
+    #[test]
+    fn test_empirical_values() {
+        let dstr = ConsumingDistribution::empirical(vec![1.0, 3.0]).unwrap();
+        assert_eq!(dstr.mean(), 2.0);
+
+        assert!(ConsumingDistribution::empirical(vec![]).is_err());
+        assert!(ConsumingDistribution::empirical(vec![1.0, -1.0]).is_err());
+        assert!(ConsumingDistribution::empirical(vec![1.0, f64::NAN]).is_err());
+        assert!(ConsumingDistribution::empirical(vec![f64::INFINITY]).is_err());
+    }
+
+    #[test]
+    fn test_mmpp_stationary() {
+        let process = Mmpp::on_off(2.0, 1.0, 3.0);
+        let stationary = process.stationary();
+
+        assert!((stationary[0] - 0.25).abs() < 1e-9);
+        assert!((stationary[1] - 0.75).abs() < 1e-9);
+        assert!((process.mean_rate() - 0.5).abs() < 1e-9);
+
+        // The third phase is left rarely and is the most likely one, though
+        // the first two phases switch fast.
+        let process = Mmpp::new(
+            vec![
+                vec![-1000.0, 1000.0, 0.0],
+                vec![1000.0, -1000.009, 0.009],
+                vec![0.001, 0.0, -0.001],
+            ],
+            vec![1.0, 1.0, 0.0],
+        );
+        let stationary = process.stationary();
+
+        let second = 1.0 / (11.0 + 9e-6);
+        assert!((stationary[0] - second * (1.0 + 9e-6)).abs() < 1e-9);
+        assert!((stationary[1] - second).abs() < 1e-9);
+        assert!((stationary[2] - 9.0 * second).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mmpp_parameters() {
+        assert!(Mmpp::try_new(vec![vec![-1.0, 1.0], vec![2.0, -2.0]], vec![1.0, 0.0]).is_ok());
+        // Not square.
+        assert!(Mmpp::try_new(vec![vec![-1.0, 1.0]], vec![1.0, 0.0]).is_err());
+        assert!(Mmpp::try_new(vec![], vec![]).is_err());
+        // Negative rate of arrivals.
+        assert!(Mmpp::try_new(vec![vec![-1.0, 1.0], vec![2.0, -2.0]], vec![-1.0, 0.0]).is_err());
+        // Negative rate of moving between phases.
+        assert!(Mmpp::try_new(vec![vec![1.0, -1.0], vec![2.0, -2.0]], vec![1.0, 0.0]).is_err());
+        // Rows do not sum to zero.
+        assert!(Mmpp::try_new(vec![vec![-1.0, 2.0], vec![2.0, -2.0]], vec![1.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn test_non_homogeneous_arrivals() {
+        let mut rng = rand::thread_rng();
+
+        // No arrivals in the first half of every period of 20.
+        let dstr = ProducingDistribution::NonHomogeneous {
+            rate: RateFunction::Piecewise {
+                schedule: vec![(0.0, 0.0), (10.0, 5.0)],
+                period: Some(20.0),
+            },
+        };
+
+        let mut time = 0.0;
+        for _ in 0..1000 {
+            let NextArrival::Arrival(delay) = dstr.sample(time, 0, &mut rng) else {
+                panic!("requests should keep arriving");
+            };
+            time += delay;
+            assert!(time.rem_euclid(20.0) >= 10.0);
+        }
+
+        let rate = time / 20.0 * 10.0 * 5.0 / 1000.0;
+        assert!(
+            (rate - 1.0).abs() < 0.2,
+            "number of arrivals should follow the rate, ratio = {}",
+            rate
+        );
+
+        // No arrivals after 10.
+        let dstr = ProducingDistribution::NonHomogeneous {
+            rate: RateFunction::Piecewise {
+                schedule: vec![(0.0, 1.0), (10.0, 0.0)],
+                period: None,
+            },
+        };
+        let mut time = 0.0;
+        while let NextArrival::Arrival(delay) = dstr.sample(time, 0, &mut rng) {
+            time += delay;
+            assert!(time < 10.0);
+        }
+    }
+
+    #[test]
+    fn test_producing_distributions() {
+        let mut rng = rand::thread_rng();
+        let samples_number = 10_000;
+
+        for dstr in [
+            ProducingDistribution::Exponential { λ: 2.0 },
+            ProducingDistribution::Degenerate { value: 3 },
+            ProducingDistribution::Renewal {
+                interval: ConsumingDistribution::Uniform { min: 1.0, max: 2.0 },
+            },
+        ] {
+            let avg = (0..samples_number)
+                .map(|_| match dstr.sample(0.0, 0, &mut rng) {
+                    NextArrival::Arrival(delay) => delay,
+                    next => panic!("requests should keep arriving, got {:?}", next),
+                })
+                .sum::<f64>()
+                / samples_number as f64;
+            assert!(
+                (avg - dstr.mean()).abs() < 0.05 * dstr.mean(),
+                "average should be nearly equal to mean of {}, avg = {}",
+                dstr.description(),
+                avg
+            );
+            assert!(dstr.variance().is_some());
+        }
+
+        let dstr = ProducingDistribution::NonHomogeneous {
+            rate: RateFunction::Piecewise {
+                schedule: vec![(0.0, 0.0), (10.0, 5.0)],
+                period: Some(20.0),
+            },
+        };
+        assert_eq!(dstr.mean(), 0.4);
+        assert_eq!(dstr.variance(), None);
+
+        let dstr = ProducingDistribution::Modulated {
+            process: Mmpp::on_off(2.0, 1.0, 3.0),
+        };
+        assert!((dstr.mean() - 2.0).abs() < 1e-9);
+        assert_eq!(dstr.variance(), None);
+    }
+
+    #[test]
+    fn test_batch_distributions() {
+        let mut rng = rand::thread_rng();
+        let samples_number = 10_000;
+
+        for dstr in [
+            BatchDistribution::Constant { size: 3 },
+            BatchDistribution::Geometric { p: 0.25 },
+            BatchDistribution::Uniform { min: 2, max: 6 },
+            BatchDistribution::Empirical {
+                weights: vec![0.5, 0.0, 0.5],
+            },
+        ] {
+            let samples = (0..samples_number)
+                .map(|_| dstr.sample(&mut rng))
+                .collect::<Vec<_>>();
+            assert!(samples.iter().all(|size| *size >= 1));
+
+            let avg = samples.iter().sum::<usize>() as f64 / samples_number as f64;
+            assert!(
+                (avg - dstr.mean()).abs() < 0.2,
+                "average should be nearly equal to mean of {:?}, avg = {}",
+                dstr,
+                avg
+            );
+        }
+    }
 }
@@ -2,40 +2,79 @@ use std::cmp::Reverse;
 
 use crate::request::Request;
 
-#[derive(Debug, Clone, Eq, PartialEq, PartialOrd)]
+/// Type of the event in the system.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd)]
 pub enum EventType {
     Arrival,
     Departure,
+    /// Patience of the waiting request ran out.
+    Renege,
+    /// Blocked request tries to enter the system again from the orbit.
+    Retrial,
+    /// Request served by another system of a
+    /// [`Network`](crate::network::Network) entered the system.
+    Routed,
+    /// Markov-modulated arrival process of a class moved to another phase.
+    PhaseChange,
+    /// Node returned from vacation.
+    VacationEnd,
+    /// Node finished setup and is ready to serve.
+    SetupEnd,
+    /// Node broke down.
+    Failure,
+    /// Node was repaired after failure.
+    Repair,
 }
 
-///! Represents event in the system.
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+/// Represents event in the system.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Event {
-    ///! Time to which event is scheduled.
-    ///!
-    ///! If event is `Arrival` then it is time of arrival.
-    ///! If event is `Departure` then it is time of departure.
+    /// Time to which event is scheduled.
+    ///
+    /// If event is `Arrival` or `Routed` then it is time of arrival.
+    /// If event is `Departure` then it is time of departure.
+    /// If event is `Renege` then it is time the request leaves the queue.
+    /// If event is `Retrial` then it is time of the repeated attempt.
+    /// If event is `PhaseChange` then it is time the phase changes.
+    /// If event is `VacationEnd`, `SetupEnd` or `Repair` then it is time the
+    /// node is ready to serve.
+    /// If event is `Failure` then it is time the node breaks down.
     pub time: f64,
-    ///! Request to which event is related.
-    ///!
-    ///! If event is `Arrival` then it is request which is arriving.
-    ///! If event is `Departure` then it is request which is departing.
-    pub request: Request,
-    ///! Type of the event.
+    /// Request to which event is related, `None` for events of nodes.
+    ///
+    /// If event is `Arrival` or `Routed` then it is request which is arriving.
+    /// If event is `Departure` then it is request which is departing.
+    /// If event is `Renege` then it is request which is waiting.
+    /// If event is `Retrial` then it is request which is in the orbit.
+    /// If event is `PhaseChange` then it is request of the class which will
+    /// arrive next.
+    pub request: Option<Request>,
+    /// Index of the node to which event is related, `None` for events of
+    /// requests.
+    pub node: Option<usize>,
+    /// Type of the event.
     pub r#type: EventType,
 }
 
+impl Event {
+    /// Checks if the event is of `r#type` and related to `node`.
+    pub fn is_of_node(&self, r#type: EventType, node: usize) -> bool {
+        self.r#type == r#type && self.node == Some(node)
+    }
+}
+
 impl Eq for Event {}
 
 impl Ord for Event {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        if self.time > other.time {
-            std::cmp::Ordering::Less
-        } else if self.time < other.time {
-            std::cmp::Ordering::Greater
-        } else {
-            std::cmp::Ordering::Equal
-        }
+        self.time.total_cmp(&other.time)
+    }
+}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -60,8 +99,23 @@ impl EventsQueue {
         self.heap.pop().map(|Reverse(event)| event)
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.heap.is_empty()
+    pub fn peek(&self) -> Option<&Event> {
+        self.heap.peek().map(|Reverse(event)| event)
+    }
+
+    /// Cancels the first found event matching `predicate` and returns it.
+    pub fn remove(&mut self, mut predicate: impl FnMut(&Event) -> bool) -> Option<Event> {
+        let mut removed = None;
+
+        self.heap.retain(|Reverse(event)| {
+            if removed.is_none() && predicate(event) {
+                removed = Some(event.clone());
+                return false;
+            }
+            true
+        });
+
+        removed
     }
 }
 
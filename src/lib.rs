@@ -1,5 +1,13 @@
+pub mod class;
+pub mod discipline;
 pub mod distributions;
 mod events;
+pub use events::EventType;
+mod linear;
+pub mod network;
+pub mod observer;
 mod request;
 pub use request::*;
+pub mod server;
+pub mod summary;
 pub mod system;
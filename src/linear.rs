@@ -0,0 +1,31 @@
+/// Solves linear equations given by augmented `matrix`, whose rows are
+/// coefficients followed by the right-hand side, by Gauss-Jordan elimination
+/// with partial pivoting. Returns `None` if the equations have no single
+/// solution.
+pub(crate) fn solve(mut matrix: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let n = matrix.len();
+
+    for column in 0..n {
+        let pivot = (column..n).max_by(|a, b| {
+            matrix[*a][column]
+                .abs()
+                .total_cmp(&matrix[*b][column].abs())
+        })?;
+        if matrix[pivot][column].abs() <= f64::EPSILON {
+            return None;
+        }
+        matrix.swap(column, pivot);
+
+        let pivot = matrix[column].clone();
+        for (index, row) in matrix.iter_mut().enumerate() {
+            if index != column {
+                let factor = row[column] / pivot[column];
+                for (value, subtrahend) in row.iter_mut().zip(&pivot).skip(column) {
+                    *value -= factor * subtrahend;
+                }
+            }
+        }
+    }
+
+    Some((0..n).map(|j| matrix[j][n] / matrix[j][j]).collect())
+}
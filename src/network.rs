@@ -0,0 +1,446 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+
+use crate::{
+    linear,
+    observer::Observer,
+    request::Request,
+    summary::NetworkSummary,
+    system::{SimulationEvent, System},
+};
+
+/// Open network of [`System`]s, called stations, where requests served by a
+/// station move to another station or leave the network with the given
+/// probabilities.
+///
+/// Requests enter the network by arrival processes of the stations, use
+/// [`System::without_arrivals`] for stations which receive requests only
+/// from other stations.
+#[derive(Debug)]
+pub struct Network<R = StdRng> {
+    stations: Vec<System<R>>,
+    /// Requests which left each station after service and are not routed
+    /// yet.
+    departures: Vec<Rc<RefCell<Departures>>>,
+    /// `routing[i][j]` is the probability that request served by station
+    /// `i` moves to station `j`, the rest of the row is the probability to
+    /// leave the network.
+    routing: Vec<Vec<f64>>,
+    /// Stream of random numbers used to choose the next station.
+    routing_rng: R,
+    /// Processed events which were not yielded yet.
+    pending: VecDeque<NetworkEvent>,
+    summary: NetworkSummary,
+}
+
+/// Event processed by a station of the [`Network`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkEvent {
+    /// Index of the station.
+    pub station: usize,
+    /// Event together with the state of the station right after it.
+    pub event: SimulationEvent,
+}
+
+/// Collects requests served by a station.
+#[derive(Debug, Default)]
+struct Departures(Vec<(f64, Request)>);
+
+impl Observer for Departures {
+    fn on_departure(&mut self, time: f64, request: &Request) {
+        self.0.push((time, *request));
+    }
+}
+
+impl<R: RngCore + SeedableRng> Network<R> {
+    /// Creates network of `stations` connected by `routing` matrix, where
+    /// `routing[i][j]` is the probability that request served by station `i`
+    /// moves to station `j`. Requests leave the network with the probability
+    /// left in the row.
+    ///
+    /// `seed` is used for routing only, stations use their own seeds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `routing` is not a square matrix with a row per station,
+    /// if any probability is negative or if any row sums to more than 1.
+    pub fn new(
+        stations: impl IntoIterator<Item = System<R>>,
+        routing: Vec<Vec<f64>>,
+        seed: u64,
+    ) -> Self {
+        let mut stations = stations.into_iter().collect::<Vec<_>>();
+        assert!(
+            routing.len() == stations.len()
+                && routing.iter().all(|row| row.len() == stations.len()),
+            "Routing should be a square matrix with a row per station"
+        );
+        for row in &routing {
+            assert!(
+                row.iter().all(|probability| *probability >= 0.0)
+                    && row.iter().sum::<f64>() <= 1.0 + f64::EPSILON,
+                "Routing probabilities should be non-negative and sum to at most 1, got {:?}",
+                row
+            );
+        }
+
+        let departures = stations
+            .iter_mut()
+            .map(|station| {
+                let departures = Rc::new(RefCell::new(Departures::default()));
+                station.add_observer(departures.clone());
+                departures
+            })
+            .collect();
+
+        Self {
+            stations,
+            departures,
+            routing,
+            routing_rng: R::seed_from_u64(seed),
+            pending: VecDeque::new(),
+            summary: NetworkSummary::default(),
+        }
+    }
+
+    /// Creates network of stations in a line: requests served by a station
+    /// move to the next one and leave the network after the last one.
+    pub fn tandem(stations: impl IntoIterator<Item = System<R>>, seed: u64) -> Self {
+        let stations = stations.into_iter().collect::<Vec<_>>();
+        let routing = (0..stations.len())
+            .map(|from| {
+                (0..stations.len())
+                    .map(|to| if to == from + 1 { 1.0 } else { 0.0 })
+                    .collect()
+            })
+            .collect();
+
+        Self::new(stations, routing, seed)
+    }
+
+    /// Stations of the network in the order they were given.
+    pub fn stations(&self) -> &[System<R>] {
+        &self.stations
+    }
+
+    /// Aggregated statistics from the start of simulation.
+    pub fn summary(&self) -> NetworkSummary {
+        NetworkSummary {
+            stations: self
+                .stations
+                .iter()
+                .map(|station| station.summary().clone())
+                .collect(),
+            ..self.summary.clone()
+        }
+    }
+
+    /// Time of the event which will be yielded by the next call of
+    /// [`Iterator::next`], infinity if there are no events.
+    pub fn next_event_time(&mut self) -> f64 {
+        match self.pending.front() {
+            Some(pending) => pending.event.time,
+            None => self
+                .stations
+                .iter_mut()
+                .map(System::next_event_time)
+                .fold(f64::INFINITY, f64::min),
+        }
+    }
+
+    /// Runs simulation until time `horizon` and returns statistics from the
+    /// start of simulation. Events scheduled after it are left unprocessed.
+    pub fn run_until(&mut self, horizon: f64) -> NetworkSummary {
+        while self.next_event_time() <= horizon {
+            self.next();
+        }
+        for station in &mut self.stations {
+            station.run_until(horizon);
+        }
+        self.summary.time = self.summary.time.max(horizon);
+
+        self.summary()
+    }
+
+    /// Processes the earliest event of the stations and routes requests
+    /// served by it. Returns `false` if there are no events.
+    fn step(&mut self) -> bool {
+        let Some((station, time)) = self
+            .stations
+            .iter_mut()
+            .map(System::next_event_time)
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        else {
+            return false;
+        };
+        if time == f64::INFINITY {
+            return false;
+        }
+
+        let event = self.stations[station]
+            .next()
+            .expect("station has an event at finite time");
+        self.summary.time = event.time;
+        self.pending.push_back(NetworkEvent { station, event });
+
+        self.route(station);
+        true
+    }
+
+    /// Moves requests served by station `from` to the next stations, or out
+    /// of the network.
+    fn route(&mut self, from: usize) {
+        let departures = std::mem::take(&mut self.departures[from].borrow_mut().0);
+
+        for (time, request) in departures {
+            match self.destination(from) {
+                Some(to) => {
+                    self.summary.transfers += 1;
+                    let event = self.stations[to].receive(time, &request);
+                    self.pending.push_back(NetworkEvent { station: to, event });
+                }
+                None => {
+                    let entered_at = request.entered_at.expect("served request entered");
+                    self.summary.completed += 1;
+                    self.summary.sojourn_time_sum += time - entered_at;
+                }
+            }
+        }
+    }
+
+    /// Chooses station to which request served by station `from` moves,
+    /// `None` if it leaves the network.
+    fn destination(&mut self, from: usize) -> Option<usize> {
+        let mut chance = self.routing_rng.gen::<f64>();
+        for (to, probability) in self.routing[from].iter().enumerate() {
+            if chance < *probability {
+                return Some(to);
+            }
+            chance -= probability;
+        }
+
+        None
+    }
+}
+
+impl<R: RngCore + SeedableRng> Iterator for Network<R> {
+    type Item = NetworkEvent;
+
+    /// Yields events of all stations in order of time, `None` if none of
+    /// the stations has events.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() && !self.step() {
+            return None;
+        }
+        self.pending.pop_front()
+    }
+}
+
+/// Stationary characteristics of a station of the open Jackson network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JacksonStation {
+    /// Total rate of arrivals from outside and from other stations,
+    /// \(\lambda_j\).
+    pub arrival_rate: f64,
+    /// Fraction of busy nodes, \(\rho_j = \lambda_j / (n_j \mu_j)\).
+    pub utilization: f64,
+    /// Mean number of requests at the station, \(L_j\).
+    pub mean_in_system: f64,
+    /// Mean time a request spends at the station per visit, \(W_j\).
+    pub sojourn_mean: f64,
+}
+
+/// Product-form solution of the open Jackson network, where requests arrive
+/// from outside by Poisson processes and each station is M/M/n with
+/// unbounded queue. Stations then behave as independent M/M/n queues with
+/// arrival rates given by the traffic equations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductForm {
+    /// Characteristics of each station.
+    pub stations: Vec<JacksonStation>,
+    /// Total rate of arrivals from outside, \(\gamma\).
+    pub external_rate: f64,
+}
+
+impl ProductForm {
+    /// Solves the network with `external_rates` of arrivals to each station,
+    /// `routing` matrix as in [`Network::new`] and `servers` given as the
+    /// number of nodes and service rate of each station.
+    ///
+    /// Returns `None` if any station is overloaded, so there is no
+    /// stationary regime.
+    pub fn new(
+        external_rates: &[f64],
+        routing: &[Vec<f64>],
+        servers: &[(usize, f64)],
+    ) -> Option<Self> {
+        let stations = traffic_rates(external_rates, routing)
+            .into_iter()
+            .zip(servers)
+            .map(|(arrival_rate, (nodes, μ))| {
+                let mean_in_system = mmn_mean_in_system(arrival_rate, *nodes, *μ)?;
+
+                Some(JacksonStation {
+                    arrival_rate,
+                    utilization: arrival_rate / (*nodes as f64 * μ),
+                    mean_in_system,
+                    sojourn_mean: mean_in_system / arrival_rate,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            stations,
+            external_rate: external_rates.iter().sum(),
+        })
+    }
+
+    /// Mean number of requests in the network, \(L = \sum_j L_j\).
+    pub fn mean_in_network(&self) -> f64 {
+        self.stations
+            .iter()
+            .map(|station| station.mean_in_system)
+            .sum()
+    }
+
+    /// Mean time a request spends in the network, \(W = L / \gamma\) by
+    /// Little's law.
+    pub fn sojourn_mean(&self) -> f64 {
+        self.mean_in_network() / self.external_rate
+    }
+}
+
+/// Solves the traffic equations \(\lambda_j = \gamma_j + \sum_i \lambda_i
+/// p_{ij}\) for total arrival rates of stations.
+///
+/// # Panics
+///
+/// Panics if the equations have no single solution, i.e. some requests
+/// never leave the network.
+pub fn traffic_rates(external_rates: &[f64], routing: &[Vec<f64>]) -> Vec<f64> {
+    let n = external_rates.len();
+
+    // Augmented matrix of (I - Pᵀ) λ = γ.
+    let matrix = (0..n)
+        .map(|j| {
+            let mut row = (0..n)
+                .map(|i| if i == j { 1.0 } else { 0.0 } - routing[i][j])
+                .collect::<Vec<_>>();
+            row.push(external_rates[j]);
+            row
+        })
+        .collect::<Vec<_>>();
+
+    linear::solve(matrix).expect("Traffic equations have no single solution")
+}
+
+/// Mean number of requests in M/M/n queue, `None` if it is overloaded.
+fn mmn_mean_in_system(λ: f64, nodes: usize, μ: f64) -> Option<f64> {
+    let load = λ / μ;
+    let utilization = load / nodes as f64;
+    if utilization >= 1.0 {
+        return None;
+    }
+
+    // Terms a^k / k! for k < n, and the probability to wait by Erlang C
+    // formula.
+    let mut term = 1.0;
+    let mut sum = 0.0;
+    for k in 0..nodes {
+        sum += term;
+        term *= load / (k + 1) as f64;
+    }
+    let waiting = term / (1.0 - utilization);
+    let waiting_probability = waiting / (sum + waiting);
+
+    Some(load + waiting_probability * utilization / (1.0 - utilization))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distributions::{ConsumingDistribution, ProducingDistribution};
+    use crate::system::QueueCapacity;
+
+    fn station(μ: f64, λ: f64, seed: u64) -> System {
+        System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Exponential { λ: μ },
+            ProducingDistribution::Exponential { λ },
+            seed,
+        )
+    }
+
+    #[test]
+    fn test_tandem() {
+        // Requests arrive at 3, 6, 9 and are served for 1 tick by the first
+        // station and for 2 ticks by the second one.
+        let first = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 1.0 },
+            ProducingDistribution::Degenerate { value: 3 },
+            0,
+        );
+        let second = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 0.5 },
+            ProducingDistribution::Degenerate { value: 3 },
+            0,
+        )
+        .without_arrivals();
+        let mut network = Network::<StdRng>::tandem([first, second], 0);
+
+        let events = network.by_ref().take(3).collect::<Vec<_>>();
+        assert_eq!(events[1].station, 0);
+        assert_eq!(events[2].station, 1);
+        assert_eq!(events[2].event.r#type, crate::EventType::Routed);
+        assert_eq!(events[2].event.time, 4.0);
+
+        let summary = network.run_until(10.5);
+        assert_eq!(summary.offered(), 3);
+        assert_eq!(summary.transfers, 3);
+        assert_eq!(summary.completed, 2);
+        assert_eq!(summary.sojourn_mean(), 3.0);
+        assert_eq!(summary.stations[1].offered, 3);
+        assert_eq!(summary.stations[1].completed, 2);
+    }
+
+    #[test]
+    fn test_traffic_rates() {
+        // Half of requests served by the second station return to the first.
+        let routing = vec![vec![0.0, 1.0], vec![0.5, 0.0]];
+        assert_eq!(traffic_rates(&[1.0, 0.0], &routing), vec![2.0, 2.0]);
+
+        let solution = ProductForm::new(&[1.0, 0.0], &routing, &[(1, 4.0), (3, 1.0)]).unwrap();
+        assert_eq!(solution.stations[0].utilization, 0.5);
+        assert_eq!(solution.stations[0].mean_in_system, 1.0);
+        assert!((solution.stations[1].mean_in_system - 2.0 - 8.0 / 9.0).abs() < 1e-12);
+
+        assert_eq!(
+            ProductForm::new(&[1.0, 0.0], &routing, &[(1, 1.0), (3, 1.0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_jackson_network() {
+        let routing = vec![vec![0.0, 0.8], vec![0.3, 0.0]];
+        let first = station(2.0, 0.5, 1);
+        let second = station(1.5, 1.0, 2).without_arrivals();
+
+        let summary =
+            Network::<StdRng>::new([first, second], routing.clone(), 3).run_until(50_000.0);
+        let solution = ProductForm::new(&[0.5, 0.0], &routing, &[(1, 2.0), (1, 1.5)]).unwrap();
+
+        let error = (summary.mean_in_network() - solution.mean_in_network()).abs();
+        assert!(error / solution.mean_in_network() < 0.05);
+        let error = (summary.sojourn_mean() - solution.sojourn_mean()).abs();
+        assert!(error / solution.sojourn_mean() < 0.05);
+    }
+}
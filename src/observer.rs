@@ -0,0 +1,181 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use crate::request::Request;
+
+/// Receives notifications about what happens inside the
+/// [`System`](crate::system::System).
+///
+/// All callbacks do nothing by default, so implementors override only the
+/// ones they are interested in.
+pub trait Observer {
+    /// Request arrived to the system, before it is accepted or blocked.
+    fn on_arrival(&mut self, _time: f64, _request: &Request) {}
+
+    /// Arrived request was rejected because the system was full, and it
+    /// is lost.
+    fn on_block(&mut self, _time: f64, _request: &Request) {}
+
+    /// Arrived request found the system full and joined the orbit to try
+    /// again later.
+    fn on_orbit(&mut self, _time: f64, _request: &Request) {}
+
+    /// Arrived request refused to join the queue.
+    fn on_balk(&mut self, _time: f64, _request: &Request) {}
+
+    /// Request left the queue because its patience ran out.
+    fn on_renege(&mut self, _time: f64, _request: &Request) {}
+
+    /// Request was taken by a node.
+    fn on_service_start(&mut self, _time: f64, _request: &Request) {}
+
+    /// Service of the request was interrupted by the request with higher
+    /// priority, and the request was put back into the queue.
+    fn on_preemption(&mut self, _time: f64, _request: &Request) {}
+
+    /// Request was served and left the system.
+    fn on_departure(&mut self, _time: f64, _request: &Request) {}
+
+    /// Node without work went on vacation.
+    fn on_vacation(&mut self, _time: f64, _node: usize) {}
+
+    /// Idle node started setup before serving waiting requests.
+    fn on_setup(&mut self, _time: f64, _node: usize) {}
+
+    /// Node broke down.
+    fn on_failure(&mut self, _time: f64, _node: usize) {}
+
+    /// Service of the request was interrupted by failure of its node.
+    fn on_interruption(&mut self, _time: f64, _request: &Request) {}
+
+    /// Node was repaired and is ready to serve.
+    fn on_repair(&mut self, _time: f64, _node: usize) {}
+}
+
+impl fmt::Debug for dyn Observer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Observer")
+    }
+}
+
+/// Allows to read collected data after the observer was registered in the
+/// system.
+impl<T: Observer> Observer for Rc<RefCell<T>> {
+    fn on_arrival(&mut self, time: f64, request: &Request) {
+        self.borrow_mut().on_arrival(time, request)
+    }
+
+    fn on_block(&mut self, time: f64, request: &Request) {
+        self.borrow_mut().on_block(time, request)
+    }
+
+    fn on_orbit(&mut self, time: f64, request: &Request) {
+        self.borrow_mut().on_orbit(time, request)
+    }
+
+    fn on_balk(&mut self, time: f64, request: &Request) {
+        self.borrow_mut().on_balk(time, request)
+    }
+
+    fn on_renege(&mut self, time: f64, request: &Request) {
+        self.borrow_mut().on_renege(time, request)
+    }
+
+    fn on_service_start(&mut self, time: f64, request: &Request) {
+        self.borrow_mut().on_service_start(time, request)
+    }
+
+    fn on_preemption(&mut self, time: f64, request: &Request) {
+        self.borrow_mut().on_preemption(time, request)
+    }
+
+    fn on_departure(&mut self, time: f64, request: &Request) {
+        self.borrow_mut().on_departure(time, request)
+    }
+
+    fn on_vacation(&mut self, time: f64, node: usize) {
+        self.borrow_mut().on_vacation(time, node)
+    }
+
+    fn on_setup(&mut self, time: f64, node: usize) {
+        self.borrow_mut().on_setup(time, node)
+    }
+
+    fn on_failure(&mut self, time: f64, node: usize) {
+        self.borrow_mut().on_failure(time, node)
+    }
+
+    fn on_interruption(&mut self, time: f64, request: &Request) {
+        self.borrow_mut().on_interruption(time, request)
+    }
+
+    fn on_repair(&mut self, time: f64, node: usize) {
+        self.borrow_mut().on_repair(time, node)
+    }
+}
+
+/// Same as for [`Rc<RefCell<T>>`], but the data can be read from another
+/// thread.
+impl<T: Observer> Observer for Arc<Mutex<T>> {
+    fn on_arrival(&mut self, time: f64, request: &Request) {
+        lock(self).on_arrival(time, request)
+    }
+
+    fn on_block(&mut self, time: f64, request: &Request) {
+        lock(self).on_block(time, request)
+    }
+
+    fn on_orbit(&mut self, time: f64, request: &Request) {
+        lock(self).on_orbit(time, request)
+    }
+
+    fn on_balk(&mut self, time: f64, request: &Request) {
+        lock(self).on_balk(time, request)
+    }
+
+    fn on_renege(&mut self, time: f64, request: &Request) {
+        lock(self).on_renege(time, request)
+    }
+
+    fn on_service_start(&mut self, time: f64, request: &Request) {
+        lock(self).on_service_start(time, request)
+    }
+
+    fn on_preemption(&mut self, time: f64, request: &Request) {
+        lock(self).on_preemption(time, request)
+    }
+
+    fn on_departure(&mut self, time: f64, request: &Request) {
+        lock(self).on_departure(time, request)
+    }
+
+    fn on_vacation(&mut self, time: f64, node: usize) {
+        lock(self).on_vacation(time, node)
+    }
+
+    fn on_setup(&mut self, time: f64, node: usize) {
+        lock(self).on_setup(time, node)
+    }
+
+    fn on_failure(&mut self, time: f64, node: usize) {
+        lock(self).on_failure(time, node)
+    }
+
+    fn on_interruption(&mut self, time: f64, request: &Request) {
+        lock(self).on_interruption(time, request)
+    }
+
+    fn on_repair(&mut self, time: f64, node: usize) {
+        lock(self).on_repair(time, node)
+    }
+}
+
+fn lock<T>(observer: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    observer
+        .lock()
+        .expect("Observer mutex should not be poisoned")
+}
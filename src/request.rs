@@ -10,11 +10,43 @@ pub struct Request {
     /// Time required to process [`Requset`].
     pub time_to_finish: f64,
 
+    /// Index of the [`CustomerClass`](crate::class::CustomerClass) of the request.
+    pub class: usize,
+
+    /// Priority level of the request, the lower value the higher priority.
+    pub priority: u32,
+
+    /// Identifier of the first request of the batch the request arrived
+    /// with, equal to [`Request::id`] for requests arrived alone.
+    pub batch: u64,
+
     /// Time that request was created.
     pub created_at: Option<f64>,
+    /// Time the request arrived from outside, the same as `created_at`
+    /// unless the request was routed from another system of a
+    /// [`Network`](crate::network::Network).
+    pub entered_at: Option<f64>,
 
     /// Time when request was processed
     pub started_at: Option<f64>,
+
+    /// Time of service left for the request which was interrupted by the
+    /// request with higher priority, at a node with speed 1. `None` if the
+    /// whole service is required.
+    pub remaining_time: Option<f64>,
+    /// Number of times the service of the request was interrupted.
+    pub preemptions: u32,
+    /// Number of repeated attempts to enter the system made from the orbit.
+    pub retrials: u32,
+
+    /// Index of the node which serves or served the request.
+    pub node: Option<usize>,
+    /// Time service of the request was interrupted by failure of its node,
+    /// `None` if the service is not interrupted now.
+    pub interrupted_at: Option<f64>,
+    /// Total time the request waited for its service to continue after
+    /// failures of nodes.
+    pub failure_delay: f64,
 }
 
 impl Eq for Request {}
@@ -23,11 +55,32 @@ static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 impl Request {
     pub fn new(time_to_finish: f64) -> Self {
+        Self::with_id(ID_COUNTER.fetch_add(1, Ordering::SeqCst), time_to_finish)
+    }
+
+    /// Creates [`Request`] with identifier chosen by the caller, so that
+    /// identifiers do not depend on other systems running in the process.
+    pub(crate) fn with_id(id: u64, time_to_finish: f64) -> Self {
         Self {
-            id: ID_COUNTER.fetch_add(1, Ordering::SeqCst),
+            id,
             time_to_finish,
+            class: 0,
+            priority: 0,
+            batch: id,
             created_at: None,
+            entered_at: None,
             started_at: None,
+            remaining_time: None,
+            preemptions: 0,
+            retrials: 0,
+            node: None,
+            interrupted_at: None,
+            failure_delay: 0.0,
         }
     }
+
+    /// Time of service required to finish the request.
+    pub fn time_left(&self) -> f64 {
+        self.remaining_time.unwrap_or(self.time_to_finish)
+    }
 }
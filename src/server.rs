@@ -0,0 +1,62 @@
+use crate::distributions::ConsumingDistribution;
+
+/// Node of the system with its own speed and, optionally, its own
+/// distribution of service times.
+#[derive(Debug)]
+pub struct Server {
+    /// Factor by which service times are divided at the node.
+    pub speed: f64,
+    /// Distribution of service times at the node, `None` if service times
+    /// of requests are used.
+    pub service: Option<ConsumingDistribution>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            service: None,
+        }
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the node serve requests `speed` times faster.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `speed` is not positive.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        assert!(speed > 0.0, "Speed should be positive, got {}", speed);
+
+        self.speed = speed;
+        self
+    }
+
+    /// Makes the node sample service times from `service` instead of using
+    /// the ones of requests. Service time is sampled each time a request
+    /// starts its service from the beginning.
+    pub fn with_service(mut self, service: ConsumingDistribution) -> Self {
+        self.service = Some(service);
+        self
+    }
+}
+
+/// Policy which chooses a node among idle ones.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Assignment {
+    /// The node with the highest speed, the first one among equal.
+    #[default]
+    FastestFirst,
+    /// Any idle node with equal probability.
+    Random,
+    /// The node which is idle for the longest time.
+    LongestIdle,
+    /// The first idle node after the one which was chosen the last time.
+    RoundRobin,
+}
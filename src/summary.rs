@@ -0,0 +1,687 @@
+use crate::request::Request;
+
+/// Aggregated statistics of the [`System`](crate::system::System) from the
+/// start of simulation.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    /// Time of the simulation.
+    pub time: f64,
+    /// Number of nodes in the system.
+    pub nodes_number: usize,
+    /// Maximum number of requests in the system \(n + m\), `None` if the
+    /// queue is unbounded.
+    pub max_in_system: Option<usize>,
+    /// Number of processed events.
+    pub events: u64,
+
+    /// Number of arrived requests.
+    pub offered: u64,
+    /// Number of arrival events, each of them brings a batch of requests.
+    pub batches: u64,
+    /// Number of arrived requests that were served or put in the queue.
+    pub accepted: u64,
+    /// Number of arrived requests that were rejected because the queue was
+    /// full. With the orbit only requests out of attempts are lost.
+    pub lost: u64,
+    /// Number of arrived requests that refused to join the queue.
+    pub balked: u64,
+    /// Number of requests that left the queue before their service started.
+    pub reneged: u64,
+
+    /// Number of requests that joined the orbit at least once.
+    pub orbited: u64,
+    /// Number of repeated attempts made by requests from the orbit.
+    pub retrials: u64,
+    /// Number of requests from the orbit that were eventually served.
+    pub orbited_completed: u64,
+    /// Number of requests from the orbit that were eventually lost.
+    pub orbited_lost: u64,
+
+    /// Number of requests that left the system after service.
+    pub departures: u64,
+    /// Number of requests that were served.
+    pub completed: u64,
+    /// Number of times service of a request was interrupted by the request
+    /// with higher priority.
+    pub preempted: u64,
+
+    /// Sum of waiting times in the queue of completed requests.
+    pub waiting_time_sum: f64,
+    /// Sum of squared waiting times in the queue of completed requests.
+    pub waiting_time_squares_sum: f64,
+    /// Sum of times spent in the system by completed requests.
+    pub sojourn_time_sum: f64,
+    /// Sum of waiting times in the queue of reneged requests.
+    pub reneged_waiting_time_sum: f64,
+    /// Number of batches whose service started.
+    pub batches_started: u64,
+    /// Sum of times batches waited till service of their first request
+    /// started.
+    pub batch_waiting_time_sum: f64,
+
+    /// Integral of the number of waiting requests over time.
+    pub queue_length_area: f64,
+    /// Integral of the number of busy nodes over time.
+    pub busy_nodes_area: f64,
+    /// Integral of the number of requests in service over time, which
+    /// differs from [`Summary::busy_nodes_area`] if nodes serve groups.
+    pub in_service_area: f64,
+    /// Integral of the number of requests in the orbit over time.
+    pub orbit_size_area: f64,
+    /// Time spent by the system in state \(k\), where \(k\) is the number of
+    /// requests in the queue and in service.
+    pub state_times: Vec<f64>,
+    /// Time during which the system was full, so arriving requests found
+    /// neither a free node nor a place in the queue.
+    pub full_time: f64,
+
+    /// Number of groups taken by nodes, indexed by the size of the group.
+    pub group_sizes: Vec<u64>,
+
+    /// Number of vacations taken by nodes.
+    pub vacations: u64,
+    /// Number of setups of idle nodes.
+    pub setups: u64,
+    /// Integral of the number of nodes on vacation over time.
+    pub vacation_nodes_area: f64,
+    /// Integral of the number of nodes in setup over time.
+    pub setup_nodes_area: f64,
+
+    /// Number of failures of nodes.
+    pub failures: u64,
+    /// Number of requests whose service was interrupted by failure of their
+    /// node.
+    pub interrupted: u64,
+    /// Number of requests lost because of failure of their node.
+    pub interrupted_lost: u64,
+    /// Integral of the number of failed nodes over time.
+    pub down_nodes_area: f64,
+    /// Sum of times completed requests waited for their service to continue
+    /// after failures of nodes.
+    pub failure_delay_sum: f64,
+
+    /// Statistics of each [`CustomerClass`](crate::class::CustomerClass),
+    /// where states are counted by the requests of that class only.
+    pub classes: Vec<Summary>,
+    /// Periods during which there are requests in the system.
+    pub busy_periods: BusyPeriods,
+    /// Length of the intervals of time statistics are split into, `None`
+    /// if they are not.
+    pub bucket_width: Option<f64>,
+    /// Statistics of consecutive intervals of `bucket_width` from the start
+    /// of simulation.
+    pub buckets: Vec<Bucket>,
+    /// Time the Markov-modulated arrival process spent in each phase,
+    /// empty unless requests of the class arrive by such process. Filled in
+    /// statistics of classes only.
+    pub phase_times: Vec<f64>,
+    /// Number of arrivals in each phase of the Markov-modulated arrival
+    /// process, filled in statistics of classes only.
+    pub phase_arrivals: Vec<u64>,
+    /// Statistics of each node, empty in statistics of classes.
+    pub nodes: Vec<NodeSummary>,
+}
+
+/// Statistics of an interval of time, see
+/// [`System::with_buckets`](crate::system::System::with_buckets).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bucket {
+    /// Time the interval starts.
+    pub start: f64,
+    /// Part of the interval which was simulated.
+    pub duration: f64,
+    /// Number of requests arrived during the interval.
+    pub offered: u64,
+    /// Number of requests arrived during the interval which were served.
+    pub completed: u64,
+    /// Sum of waiting times of the completed requests.
+    pub waiting_time_sum: f64,
+    /// Sum of sojourn times of the completed requests.
+    pub sojourn_time_sum: f64,
+    /// Integral of the queue length over the interval.
+    pub queue_length_area: f64,
+    /// Integral of the number of busy nodes over the interval.
+    pub busy_nodes_area: f64,
+}
+
+impl Bucket {
+    /// Number of arrived requests per unit of time.
+    pub fn arrival_rate(&self) -> f64 {
+        per_time(self.offered as f64, self.duration)
+    }
+
+    /// Mean waiting time of requests arrived during the interval.
+    pub fn waiting_mean(&self) -> f64 {
+        ratio(self.waiting_time_sum, self.completed)
+    }
+
+    /// Mean sojourn time of requests arrived during the interval.
+    pub fn sojourn_mean(&self) -> f64 {
+        ratio(self.sojourn_time_sum, self.completed)
+    }
+
+    /// Time-average number of requests waiting in the queue.
+    pub fn mean_queue_length(&self) -> f64 {
+        per_time(self.queue_length_area, self.duration)
+    }
+
+    /// Time-average number of busy nodes.
+    pub fn mean_busy_nodes(&self) -> f64 {
+        per_time(self.busy_nodes_area, self.duration)
+    }
+}
+
+/// Lengths of continuous periods of being busy. Only ended periods are
+/// counted, the ongoing one is kept in `current`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BusyPeriods {
+    /// Number of ended busy periods.
+    pub count: u64,
+    /// Total length of ended busy periods.
+    pub length_sum: f64,
+    /// Length of the longest ended busy period.
+    pub length_max: f64,
+    /// Length of the ongoing busy period so far, zero if not busy.
+    pub current: f64,
+}
+
+impl BusyPeriods {
+    /// Mean length of a busy period.
+    pub fn mean(&self) -> f64 {
+        ratio(self.length_sum, self.count)
+    }
+
+    /// Accounts `elapsed` time during which the owner was `busy` or not.
+    fn advance(&mut self, elapsed: f64, busy: bool) {
+        if busy {
+            self.current += elapsed;
+        } else if self.current > 0.0 {
+            self.count += 1;
+            self.length_sum += self.current;
+            self.length_max = self.length_max.max(self.current);
+            self.current = 0.0;
+        }
+    }
+}
+
+/// Statistics of one node of the [`System`](crate::system::System).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeSummary {
+    /// Time of the simulation.
+    pub time: f64,
+    /// Time the node was busy.
+    pub busy_time: f64,
+    /// Number of requests served by the node.
+    pub completed: u64,
+    /// Periods during which the node serves requests without a break.
+    pub busy_periods: BusyPeriods,
+}
+
+impl NodeSummary {
+    /// Fraction of time the node was busy.
+    pub fn utilization(&self) -> f64 {
+        per_time(self.busy_time, self.time)
+    }
+
+    /// Number of requests served by the node per unit of time.
+    pub fn throughput(&self) -> f64 {
+        per_time(self.completed as f64, self.time)
+    }
+}
+
+/// Aggregated statistics of the [`Network`](crate::network::Network) from
+/// the start of simulation.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkSummary {
+    /// Time of the simulation.
+    pub time: f64,
+    /// Number of requests which moved from one station to another.
+    pub transfers: u64,
+    /// Number of requests which were served and left the network.
+    pub completed: u64,
+    /// Sum of times completed requests spent in the network, from arrival
+    /// to the first station till departure from the last one.
+    pub sojourn_time_sum: f64,
+    /// Statistics of each station, where requests routed from other
+    /// stations are counted as arrived.
+    pub stations: Vec<Summary>,
+}
+
+impl NetworkSummary {
+    /// Number of requests arrived to the network from outside.
+    pub fn offered(&self) -> u64 {
+        let arrived = self
+            .stations
+            .iter()
+            .map(|station| station.offered)
+            .sum::<u64>();
+        arrived - self.transfers
+    }
+
+    /// Number of requests which left the network without being served:
+    /// blocked, balked, reneged or lost by failed nodes at any station.
+    pub fn lost(&self) -> u64 {
+        self.stations
+            .iter()
+            .map(|station| {
+                station.lost + station.balked + station.reneged + station.interrupted_lost
+            })
+            .sum()
+    }
+
+    /// Fraction of requests arrived from outside that were lost.
+    pub fn loss_probability(&self) -> f64 {
+        ratio(self.lost() as f64, self.offered())
+    }
+
+    /// Mean time completed requests spent in the network.
+    pub fn sojourn_mean(&self) -> f64 {
+        ratio(self.sojourn_time_sum, self.completed)
+    }
+
+    /// Time-average number of requests at all stations.
+    pub fn mean_in_network(&self) -> f64 {
+        self.stations.iter().map(Summary::mean_in_system).sum()
+    }
+
+    /// Number of requests leaving the network after service per unit of
+    /// time.
+    pub fn throughput(&self) -> f64 {
+        per_time(self.completed as f64, self.time)
+    }
+}
+
+impl Summary {
+    pub(crate) fn new(nodes_number: usize, max_in_system: Option<usize>) -> Self {
+        Self {
+            nodes_number,
+            max_in_system,
+            state_times: vec![0.0; max_in_system.unwrap_or(nodes_number) + 1],
+            ..Default::default()
+        }
+    }
+
+    /// Fraction of arrived requests that were lost.
+    pub fn loss_probability(&self) -> f64 {
+        ratio(self.lost as f64, self.offered)
+    }
+
+    /// Fraction of arrived requests that refused to join the queue.
+    pub fn balking_probability(&self) -> f64 {
+        ratio(self.balked as f64, self.offered)
+    }
+
+    /// Fraction of accepted requests that left the queue without being
+    /// served.
+    pub fn reneging_probability(&self) -> f64 {
+        ratio(self.reneged as f64, self.accepted)
+    }
+
+    /// Fraction of arrived requests that were not served because of
+    /// impatience, either balked or reneged.
+    pub fn abandonment_probability(&self) -> f64 {
+        ratio((self.balked + self.reneged) as f64, self.offered)
+    }
+
+    /// Mean time reneged requests waited before leaving the queue.
+    pub fn reneged_waiting_mean(&self) -> f64 {
+        ratio(self.reneged_waiting_time_sum, self.reneged)
+    }
+
+    /// Mean number of requests arrived with one arrival event.
+    pub fn batch_size_mean(&self) -> f64 {
+        ratio(self.offered as f64, self.batches)
+    }
+
+    /// Mean time a batch waited till service of its first request started.
+    pub fn batch_waiting_mean(&self) -> f64 {
+        ratio(self.batch_waiting_time_sum, self.batches_started)
+    }
+
+    /// Mean number of repeated attempts made by a request which joined the
+    /// orbit.
+    pub fn retrials_mean(&self) -> f64 {
+        ratio(self.retrials as f64, self.orbited)
+    }
+
+    /// Time-average number of requests in the orbit.
+    pub fn mean_orbit_size(&self) -> f64 {
+        self.time_average(self.orbit_size_area)
+    }
+
+    /// Mean waiting time in the queue of completed requests.
+    pub fn waiting_mean(&self) -> f64 {
+        ratio(self.waiting_time_sum, self.completed)
+    }
+
+    /// Variance of the waiting time in the queue of completed requests.
+    pub fn waiting_variance(&self) -> f64 {
+        let mean = self.waiting_mean();
+
+        ratio(self.waiting_time_squares_sum, self.completed) - mean * mean
+    }
+
+    /// Mean time spent in the system by completed requests.
+    pub fn sojourn_mean(&self) -> f64 {
+        ratio(self.sojourn_time_sum, self.completed)
+    }
+
+    /// Time-average number of requests waiting in the queue, \(L_q\).
+    pub fn mean_queue_length(&self) -> f64 {
+        self.time_average(self.queue_length_area)
+    }
+
+    /// Time-average number of busy nodes.
+    pub fn mean_busy_nodes(&self) -> f64 {
+        self.time_average(self.busy_nodes_area)
+    }
+
+    /// Fraction of nodes busy on average, \(\rho = \lambda / (n \mu)\) for
+    /// a system without losses.
+    pub fn utilization(&self) -> f64 {
+        if self.nodes_number == 0 {
+            return 0.0;
+        }
+        self.mean_busy_nodes() / self.nodes_number as f64
+    }
+
+    /// Time-average number of requests in service.
+    pub fn mean_in_service(&self) -> f64 {
+        self.time_average(self.in_service_area)
+    }
+
+    /// Time-average number of nodes on vacation.
+    pub fn mean_vacation_nodes(&self) -> f64 {
+        self.time_average(self.vacation_nodes_area)
+    }
+
+    /// Time-average number of nodes in setup.
+    pub fn mean_setup_nodes(&self) -> f64 {
+        self.time_average(self.setup_nodes_area)
+    }
+
+    /// Time-average number of failed nodes.
+    pub fn mean_down_nodes(&self) -> f64 {
+        self.time_average(self.down_nodes_area)
+    }
+
+    /// Fraction of time nodes are not failed.
+    pub fn availability(&self) -> f64 {
+        if self.nodes_number == 0 {
+            return 0.0;
+        }
+        1.0 - self.mean_down_nodes() / self.nodes_number as f64
+    }
+
+    /// Mean extra time completed requests spent in the system because of
+    /// failures of nodes, till their service continued.
+    pub fn failure_delay_mean(&self) -> f64 {
+        ratio(self.failure_delay_sum, self.completed)
+    }
+
+    /// Time-average number of requests in the queue and in service, \(L\).
+    pub fn mean_in_system(&self) -> f64 {
+        self.mean_queue_length() + self.mean_in_service()
+    }
+
+    /// Estimation of the stationary probabilities \(p_k\) that there are
+    /// \(k\) requests in the system.
+    pub fn state_probabilities(&self) -> Vec<f64> {
+        self.state_times
+            .iter()
+            .map(|time| self.time_average(*time))
+            .collect()
+    }
+
+    /// Probability that the system is full, so an arriving request is lost,
+    /// \(p_{n+m}\) if nodes serve requests one by one. Always zero for the
+    /// unbounded queue.
+    pub fn blocking_probability(&self) -> f64 {
+        self.time_average(self.full_time)
+    }
+
+    /// Probability that an arriving request has to wait in the queue: all
+    /// nodes are busy, but the system is not full.
+    pub fn waiting_probability(&self) -> f64 {
+        let end = self.max_in_system.unwrap_or(self.state_times.len());
+
+        self.state_times
+            .iter()
+            .take(end)
+            .skip(self.nodes_number)
+            .map(|time| self.time_average(*time))
+            .sum()
+    }
+
+    /// Fraction of groups of each size taken by nodes, indexed by the size.
+    pub fn group_size_distribution(&self) -> Vec<f64> {
+        let groups = self.group_sizes.iter().sum::<u64>();
+
+        self.group_sizes
+            .iter()
+            .map(|number| ratio(*number as f64, groups))
+            .collect()
+    }
+
+    /// Mean number of requests served together by a node.
+    pub fn group_size_mean(&self) -> f64 {
+        let groups = self.group_sizes.iter().sum::<u64>();
+        let requests = self
+            .group_sizes
+            .iter()
+            .enumerate()
+            .map(|(size, number)| (size as u64 * number) as f64)
+            .sum::<f64>();
+
+        ratio(requests, groups)
+    }
+
+    /// Number of completed requests per unit of time.
+    pub fn throughput(&self) -> f64 {
+        self.time_average(self.completed as f64)
+    }
+
+    /// Fraction of time the arrival process spent in each phase.
+    pub fn phase_probabilities(&self) -> Vec<f64> {
+        self.phase_times
+            .iter()
+            .map(|time| self.time_average(*time))
+            .collect()
+    }
+
+    /// Rate of arrivals observed in each phase of the arrival process.
+    pub fn phase_rates(&self) -> Vec<f64> {
+        self.phase_times
+            .iter()
+            .enumerate()
+            .map(|(phase, time)| {
+                let arrivals = self.phase_arrivals.get(phase).copied().unwrap_or_default();
+                per_time(arrivals as f64, *time)
+            })
+            .collect()
+    }
+
+    /// Moves time of the summary to `time`, accounting the state in which the
+    /// system was since the previous event.
+    ///
+    /// Requests in the orbit are not counted in the state of the system.
+    pub(crate) fn advance(
+        &mut self,
+        time: f64,
+        queue_length: usize,
+        busy_nodes: usize,
+        in_service: usize,
+        orbit_size: usize,
+        full: bool,
+    ) {
+        let elapsed = time - self.time;
+
+        if full {
+            self.full_time += elapsed;
+        }
+        self.advance_buckets(time, queue_length, busy_nodes);
+        self.queue_length_area += elapsed * queue_length as f64;
+        self.busy_nodes_area += elapsed * busy_nodes as f64;
+        self.in_service_area += elapsed * in_service as f64;
+        self.orbit_size_area += elapsed * orbit_size as f64;
+
+        let state = queue_length + in_service;
+        self.busy_periods.advance(elapsed, state > 0);
+        if state >= self.state_times.len() {
+            self.state_times.resize(state + 1, 0.0);
+        }
+        self.state_times[state] += elapsed;
+
+        self.time = time;
+    }
+
+    /// Accounts nodes which are not available since the previous event, and
+    /// which of the nodes were `busy`. Should be called before
+    /// [`Summary::advance`].
+    pub(crate) fn advance_nodes(
+        &mut self,
+        time: f64,
+        on_vacation: usize,
+        in_setup: usize,
+        down: usize,
+        busy: impl IntoIterator<Item = bool>,
+    ) {
+        let elapsed = time - self.time;
+
+        self.vacation_nodes_area += elapsed * on_vacation as f64;
+        self.setup_nodes_area += elapsed * in_setup as f64;
+        self.down_nodes_area += elapsed * down as f64;
+
+        for (node, busy) in self.nodes.iter_mut().zip(busy) {
+            if busy {
+                node.busy_time += elapsed;
+            }
+            node.busy_periods.advance(elapsed, busy);
+            node.time = time;
+        }
+    }
+
+    /// Splits time since the previous event between buckets.
+    fn advance_buckets(&mut self, time: f64, queue_length: usize, busy_nodes: usize) {
+        let Some(width) = self.bucket_width else {
+            return;
+        };
+
+        let mut start = self.time;
+        while start < time {
+            let index = bucket_index(start, width);
+            let end = ((index + 1) as f64 * width).min(time);
+            let bucket = self.bucket_mut(start).expect("statistics are split");
+
+            bucket.duration += end - start;
+            bucket.queue_length_area += (end - start) * queue_length as f64;
+            bucket.busy_nodes_area += (end - start) * busy_nodes as f64;
+
+            start = end;
+        }
+    }
+
+    /// Bucket which contains `time`, `None` if statistics are not split.
+    pub(crate) fn bucket_mut(&mut self, time: f64) -> Option<&mut Bucket> {
+        let width = self.bucket_width?;
+
+        let index = bucket_index(time, width);
+        while self.buckets.len() <= index {
+            let start = self.buckets.len() as f64 * width;
+            self.buckets.push(Bucket {
+                start,
+                ..Default::default()
+            });
+        }
+
+        self.buckets.get_mut(index)
+    }
+
+    /// Accounts time since the previous event, during which the arrival
+    /// process was in `phase`. Should be called before [`Summary::advance`].
+    pub(crate) fn advance_phase(&mut self, time: f64, phase: usize) {
+        if phase >= self.phase_times.len() {
+            self.phase_times.resize(phase + 1, 0.0);
+        }
+        self.phase_times[phase] += time - self.time;
+    }
+
+    /// Records arrival in `phase` of the arrival process.
+    pub(crate) fn arrive_in_phase(&mut self, phase: usize) {
+        if phase >= self.phase_arrivals.len() {
+            self.phase_arrivals.resize(phase + 1, 0);
+        }
+        self.phase_arrivals[phase] += 1;
+    }
+
+    /// Records group of `size` requests taken by a node.
+    pub(crate) fn take_group(&mut self, size: usize) {
+        if size >= self.group_sizes.len() {
+            self.group_sizes.resize(size + 1, 0);
+        }
+        self.group_sizes[size] += 1;
+    }
+
+    /// Records request that was served and left the system at `time`.
+    pub(crate) fn complete(&mut self, request: &Request, time: f64) {
+        let created_at = request.created_at.expect("served request was created");
+        let started_at = request.started_at.expect("served request was started");
+
+        let waiting_time = started_at - created_at;
+
+        self.completed += 1;
+        if request.retrials > 0 {
+            self.orbited_completed += 1;
+        }
+        self.waiting_time_sum += waiting_time;
+        self.waiting_time_squares_sum += waiting_time * waiting_time;
+        self.sojourn_time_sum += time - created_at;
+        self.failure_delay_sum += request.failure_delay;
+
+        if let Some(bucket) = self.bucket_mut(created_at) {
+            bucket.completed += 1;
+            bucket.waiting_time_sum += waiting_time;
+            bucket.sojourn_time_sum += time - created_at;
+        }
+    }
+
+    pub(crate) fn renege(&mut self, request: &Request, time: f64) {
+        let created_at = request.created_at.expect("reneged request was created");
+
+        self.reneged += 1;
+        self.reneged_waiting_time_sum += time - created_at;
+    }
+
+    fn time_average(&self, value: f64) -> f64 {
+        per_time(value, self.time)
+    }
+}
+
+/// Index of the bucket of `width` which contains `time`.
+fn bucket_index(time: f64, width: f64) -> usize {
+    let index = (time / width) as usize;
+    if (index + 1) as f64 * width <= time {
+        index + 1
+    } else {
+        index
+    }
+}
+
+fn per_time(value: f64, time: f64) -> f64 {
+    if time == 0.0 {
+        return 0.0;
+    }
+    value / time
+}
+
+fn ratio(value: f64, count: u64) -> f64 {
+    if count == 0 {
+        return 0.0;
+    }
+    value / count as f64
+}
@@ -1,150 +1,2950 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet};
+
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
 
 use crate::{
-    distributions::{ConsumingDistribution, ProducingDistribution},
+    class::CustomerClass,
+    discipline::{Fifo, QueueDiscipline},
+    distributions::{ArrivalProcess, ConsumingDistribution, NextArrival, TimeDistribution},
     events::{Event, EventType, EventsQueue},
+    observer::Observer,
     request::Request,
+    server::{Assignment, Server},
+    summary::{NodeSummary, Summary},
 };
 
 /// Repsenets imitating model if **Queueing System**.
+///
+/// All randomness comes from the generators owned by the system, which are
+/// derived from a single seed, so two systems created with the same seed
+/// and parameters produce the same trajectory.
 #[derive(Debug)]
-pub struct System {
+pub struct System<R = StdRng> {
     current_tick: f64,
     nodes_number: usize,
-    nodes_busy: usize,
+    nodes: Vec<Node>,
+    /// How a node is chosen among idle ones.
+    assignment: Assignment,
+    /// Node chosen the last time, used by [`Assignment::RoundRobin`].
+    last_assigned: Option<usize>,
+    /// Number of requests in service, which is greater than `nodes_busy` if
+    /// nodes serve groups.
+    in_service: usize,
+    /// Size of groups taken by nodes, `None` if requests are served one by
+    /// one.
+    bulk_service: Option<BulkService>,
+    /// Requests served together with the request whose identifier is the
+    /// key, which is the one with scheduled departure.
+    groups: HashMap<u64, Vec<Request>>,
+    /// Vacations of nodes without work, `None` if such nodes wait idle.
+    vacation: Option<Vacation>,
+    /// Distribution of setup times of idle nodes, `None` if they start
+    /// serving immediately.
+    setup: Option<ConsumingDistribution>,
+    /// Number of requests the node serving the request whose identifier is
+    /// the key may take before its next vacation, for gated service.
+    gates: HashMap<u64, usize>,
+    /// Failures and repairs of nodes, `None` if nodes never fail.
+    breakdowns: Option<Breakdowns>,
+    /// Requests whose service waits for repair of the node which is the key,
+    /// see [`Interruption::Resume`].
+    suspended: HashMap<usize, Request>,
+    /// Waiting requests whose reneging is scheduled. Reneging of requests
+    /// which left the queue is skipped instead of being removed from
+    /// `events_queue`.
+    impatient: HashSet<u64>,
 
     events_queue: EventsQueue,
-    queue: VecDeque<Request>,
+    queue: Box<dyn QueueDiscipline>,
+    /// Maximum number of requests waiting in `queue`.
+    queue_capacity: QueueCapacity,
+    /// What happens to a request in service when a request with higher
+    /// priority arrives.
+    preemption: Preemption,
+    /// Where blocked requests wait to try again, `None` if they are lost.
+    orbit: Option<Orbit>,
+    /// Number of requests in the orbit.
+    orbit_size: usize,
+    /// How batches are accepted when there is not enough place for all
+    /// their requests.
+    batch_acceptance: BatchAcceptance,
+    /// Batches none of which requests started service, with the number of
+    /// their requests waiting in the queue.
+    pending_batches: HashMap<u64, usize>,
+
+    /// Classes of requests, the first one is given to the constructor.
+    classes: Vec<ClassSource<R>>,
+    /// Arrivals of all classes are scheduled.
+    started: bool,
+    /// Requests of the classes arrive from outside, see
+    /// [`System::without_arrivals`].
+    arrivals: bool,
+
+    /// Stream of random numbers used by the queue discipline.
+    discipline_rng: R,
+    /// Probabilities to join the queue of the given length, see
+    /// [`System::with_balking`].
+    join_probabilities: Vec<f64>,
+    /// Stream of random numbers used to decide whether a request balks.
+    balking_rng: R,
+    /// Stream of random numbers used for times between retrials.
+    orbit_rng: R,
+    /// Stream of random numbers used for lengths of vacations.
+    vacation_rng: R,
+    /// Stream of random numbers used for setup times.
+    setup_rng: R,
+    /// Stream of random numbers used for uptimes and repair times.
+    breakdown_rng: R,
+    /// Stream of random numbers used to choose idle nodes.
+    assignment_rng: R,
+    /// Stream of random numbers used for service times of nodes.
+    server_rng: R,
+    /// Generator of seeds for streams of classes added after creation.
+    seeder: R,
+    /// Identifier of the next created [`Request`].
+    next_request_id: u64,
+
+    summary: Summary,
+
+    observers: Vec<Box<dyn Observer>>,
+}
+
+/// Source of requests of one [`CustomerClass`].
+#[derive(Debug)]
+struct ClassSource<R> {
+    class: CustomerClass,
+
+    /// Stream of random numbers used for inter-arrival times.
+    arrival_rng: R,
+    /// Stream of random numbers used for service times.
+    service_rng: R,
+    /// Stream of random numbers used for patience times.
+    patience_rng: R,
+    /// Stream of random numbers used for batch sizes.
+    batch_rng: R,
+
+    /// Number of requests of the class waiting in the queue.
+    waiting: usize,
+    /// Number of requests of the class being served.
+    busy: usize,
+    /// Number of requests of the class in the orbit.
+    orbiting: usize,
+    /// Phase of the arrival process, `None` if arrivals do not depend on a
+    /// phase.
+    phase: Option<usize>,
+}
+
+impl<R: RngCore + SeedableRng> ClassSource<R> {
+    /// # Panics
+    ///
+    /// Panics if the class has both finite population and arrivals which
+    /// depend on a phase.
+    fn new(class: CustomerClass, seeder: &mut R) -> Self {
+        let mut arrival_rng = R::seed_from_u64(seeder.next_u64());
+
+        let phase = match class.arrival.phases() {
+            1 => None,
+            _ => {
+                assert!(
+                    class.population.is_none(),
+                    "Arrivals of finite population which depend on a phase are not supported"
+                );
+                Some(class.arrival.initial_phase(&mut arrival_rng))
+            }
+        };
+
+        Self {
+            class,
+            arrival_rng,
+            service_rng: R::seed_from_u64(seeder.next_u64()),
+            patience_rng: R::seed_from_u64(seeder.next_u64()),
+            batch_rng: R::seed_from_u64(seeder.next_u64()),
+            waiting: 0,
+            busy: 0,
+            orbiting: 0,
+            phase,
+        }
+    }
+}
+
+/// Node of the system together with what it is doing.
+#[derive(Debug)]
+struct Node {
+    server: Server,
+    state: NodeState,
+    /// Time the node entered its current state.
+    since: f64,
+    /// Scheduled departure of the group the node serves: its time and the
+    /// first request of the group.
+    departure: Option<(f64, Request)>,
+}
+
+impl Node {
+    fn new(server: Server) -> Self {
+        Self {
+            server,
+            state: NodeState::Idle,
+            since: 0.0,
+            departure: None,
+        }
+    }
+}
+
+/// What a node is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeState {
+    Idle,
+    Busy,
+    Vacation,
+    Setup,
+    /// Node failed and is being repaired.
+    Down,
+}
+
+/// Condition on which [`System::run`] stops the simulation.
+///
+/// Counters are relative to the moment [`System::run`] was called, while the
+/// time horizon is absolute.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopCondition {
+    /// Stop at the given time, events after it are not processed.
+    Time(f64),
+    /// Stop after the given number of events of any type.
+    Events(u64),
+    /// Stop after the given number of arrivals, including lost ones.
+    Arrivals(u64),
+    /// Stop after the given number of departures.
+    Departures(u64),
+    /// Stop after the given number of served requests.
+    Completed(u64),
+}
+
+/// Number of places for requests that wait for a free node.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueCapacity {
+    /// There is no waiting room, every request that finds all nodes busy is
+    /// lost (M/G/n/0, Erlang-B).
+    Loss,
+    /// At most given number of requests can wait (M/G/n/m).
+    Finite(usize),
+    /// Every request that finds all nodes busy waits (M/G/n/∞).
+    Unbounded,
+}
+
+impl QueueCapacity {
+    /// Maximum number of waiting requests, `None` if it is unlimited.
+    pub fn limit(&self) -> Option<usize> {
+        match self {
+            Self::Loss => Some(0),
+            Self::Finite(capacity) => Some(*capacity),
+            Self::Unbounded => None,
+        }
+    }
+
+    /// Checks if one more request fits into queue with `len` waiting requests.
+    pub fn fits(&self, len: usize) -> bool {
+        self.limit().is_none_or(|limit| len < limit)
+    }
+}
+
+impl From<usize> for QueueCapacity {
+    fn from(capacity: usize) -> Self {
+        Self::Finite(capacity)
+    }
+}
+
+/// How a batch of requests is accepted when there is not enough place in
+/// the system for all of them.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchAcceptance {
+    /// Requests of the batch are accepted one by one until the system is
+    /// full, the rest are blocked.
+    #[default]
+    Partial,
+    /// Either all requests of the batch are accepted, or all are blocked.
+    Whole,
+}
+
+/// Bulk service rule: a free node waits until there are at least `min`
+/// requests in the queue, and takes up to `max` of them to serve as one
+/// group.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BulkService {
+    /// Minimum size of a group, \(a\).
+    pub min: usize,
+    /// Maximum size of a group, \(b\).
+    pub max: usize,
+}
+
+/// Vacations of nodes: a node which has no requests to serve leaves for a
+/// random time, and does not take requests arriving meanwhile.
+#[derive(Debug)]
+pub struct Vacation {
+    /// Distribution of lengths of vacations.
+    pub duration: ConsumingDistribution,
+    /// What a node does if there are no requests when it returns.
+    pub policy: VacationPolicy,
+    /// Which requests a node serves after it returns.
+    pub service: VacationService,
+}
+
+impl Vacation {
+    pub fn new(duration: ConsumingDistribution) -> Self {
+        Self {
+            duration,
+            policy: VacationPolicy::default(),
+            service: VacationService::default(),
+        }
+    }
+
+    /// Sets what a node does if there are no requests when it returns.
+    pub fn with_policy(mut self, policy: VacationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets which requests a node serves after it returns.
+    pub fn with_service(mut self, service: VacationService) -> Self {
+        self.service = service;
+        self
+    }
+}
+
+/// What a node returning from vacation does if there are no requests to
+/// serve.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VacationPolicy {
+    /// Node waits idle and serves the next arrived request, it takes
+    /// another vacation only after serving.
+    Single,
+    /// Node takes vacations until there are requests waiting.
+    #[default]
+    Multiple,
+}
+
+/// Which requests a node serves before its next vacation.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VacationService {
+    /// Node serves until there are no requests waiting.
+    #[default]
+    Exhaustive,
+    /// Node serves only requests which were waiting when it started to
+    /// serve, the ones arrived later wait for the next time.
+    Gated,
+}
+
+/// Failures of nodes: each node breaks down after a random uptime, counted
+/// whatever the node does, and returns after a random repair time.
+#[derive(Debug)]
+pub struct Breakdowns {
+    /// Distribution of times between repair and the next failure of a node.
+    pub uptime: ConsumingDistribution,
+    /// Distribution of repair times.
+    pub repair: ConsumingDistribution,
+    /// What happens to requests served by the failed node.
+    pub interruption: Interruption,
+}
+
+impl Breakdowns {
+    pub fn new(uptime: ConsumingDistribution, repair: ConsumingDistribution) -> Self {
+        Self {
+            uptime,
+            repair,
+            interruption: Interruption::default(),
+        }
+    }
+
+    /// Sets what happens to requests served by the failed node.
+    pub fn with_interruption(mut self, interruption: Interruption) -> Self {
+        self.interruption = interruption;
+        self
+    }
+}
+
+/// What happens to requests in service when their node fails.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interruption {
+    /// Requests return to the queue and are served from the beginning by
+    /// any node. Requests which find no place in the queue are lost, as
+    /// with [`Interruption::Lose`].
+    #[default]
+    Requeue,
+    /// Requests stay at the node and continue their service after repair.
+    Resume,
+    /// Requests leave the system without being served.
+    Lose,
+}
+
+/// Reason why arrived request did not enter the system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Refusal {
+    Blocked,
+    Orbited,
+    Balked,
+}
+
+/// Orbit of a retrial queue: requests which find the system full stay in
+/// it and repeat their attempts after random delays, instead of being lost.
+/// The orbit is unbounded.
+#[derive(Debug)]
+pub struct Orbit {
+    /// Distribution of times between attempts of a request.
+    pub retrial: ConsumingDistribution,
+    /// Maximum number of repeated attempts of a request, after which it is
+    /// lost. `None` if requests retry until they succeed.
+    pub max_retrials: Option<u32>,
+}
 
-    request_finish_dsrt: ConsumingDistribution,
-    request_arrival_dsrt: ProducingDistribution,
+impl Orbit {
+    pub fn new(retrial: ConsumingDistribution) -> Self {
+        Self {
+            retrial,
+            max_retrials: None,
+        }
+    }
+
+    /// Limits number of repeated attempts of a request.
+    pub fn with_max_retrials(mut self, max_retrials: u32) -> Self {
+        self.max_retrials = Some(max_retrials);
+        self
+    }
+}
+
+/// What happens to the request in service when a request with higher
+/// priority finds all nodes busy.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preemption {
+    /// Request in service is never interrupted, priority only affects the
+    /// order of waiting requests.
+    #[default]
+    None,
+    /// Interrupted request returns to the queue and later continues its
+    /// service from the point it was interrupted.
+    Resume,
+    /// Interrupted request returns to the queue and later is served from
+    /// the beginning.
+    Repeat,
+}
 
-    finished_requests: Option<Request>,
+/// Event processed by the [`System`] together with the state of the system
+/// right after it.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationEvent {
+    /// Time of the event.
+    pub time: f64,
+    /// Type of the event.
+    pub r#type: EventType,
+    /// Request to which event is related, `None` for events of nodes.
+    ///
+    /// If event is `Arrival` or `Routed` then it is request which arrived.
+    /// If event is `Departure` then it is request which was served.
+    pub request: Option<Request>,
+    /// Index of the node to which event is related, `None` for events of
+    /// requests.
+    pub node: Option<usize>,
+    /// Arrived request was rejected because the system was full.
+    ///
+    /// For batch arrivals this and the following flags are about the first
+    /// request of the batch.
+    pub blocked: bool,
+    /// Arrived request found the system full and joined the orbit.
+    pub orbited: bool,
+    /// Arrived request refused to join the queue.
+    pub balked: bool,
+    /// Number of requests waiting in the queue after the event.
+    pub queue_length: usize,
+    /// Number of busy nodes after the event.
+    pub busy_nodes: usize,
+    /// Number of requests in service after the event, the same as
+    /// `busy_nodes` unless nodes serve groups.
+    pub in_service: usize,
+    /// Number of requests in the orbit after the event, they are not
+    /// counted as requests in the system.
+    pub orbit_size: usize,
+    /// Number of failed nodes after the event.
+    pub down_nodes: usize,
 }
 
-#[derive(Debug, Default)]
-pub struct Stats {
-    /// Current tick
-    pub current_tick: f64,
+impl SimulationEvent {
     /// Sum of requests in queue + requests that are being processed.
-    pub requests_in_system: usize,
-    /// Finished requests:
-    pub finished_request: Option<Request>,
+    pub fn requests_in_system(&self) -> usize {
+        self.queue_length + self.in_service
+    }
 }
 
 impl System {
-    /// Creates new [`System`] instance.
+    /// Creates new [`System`] instance which uses [`StdRng`] seeded with
+    /// `seed` as the source of randomness.
     pub fn new(
         nodes_number: usize,
-        queue_capacity: usize,
+        queue_capacity: impl Into<QueueCapacity>,
         request_finish_dsrt: ConsumingDistribution,
-        request_arrival_dsrt: ProducingDistribution,
+        request_arrival_dsrt: impl ArrivalProcess + 'static,
+        seed: u64,
     ) -> Self {
-        Self {
-            current_tick: 0.0,
-            nodes_busy: 0,
-            events_queue: EventsQueue::new(),
-            queue: VecDeque::with_capacity(queue_capacity),
-            finished_requests: None,
+        Self::from_seed(
             nodes_number,
+            queue_capacity,
             request_finish_dsrt,
             request_arrival_dsrt,
+            seed,
+        )
+    }
+}
+
+impl<R: RngCore + SeedableRng> System<R> {
+    /// Creates new [`System`] instance with random number generator of type
+    /// `R` seeded with `seed`.
+    ///
+    /// Arrivals and service times are sampled from separate streams, all
+    /// derived from `seed`.
+    ///
+    /// Requests created by the system belong to the class with index 0, more
+    /// classes can be added with [`System::with_class`].
+    pub fn from_seed(
+        nodes_number: usize,
+        queue_capacity: impl Into<QueueCapacity>,
+        request_finish_dsrt: ConsumingDistribution,
+        request_arrival_dsrt: impl ArrivalProcess + 'static,
+        seed: u64,
+    ) -> Self {
+        Self::from_class(
+            nodes_number,
+            queue_capacity,
+            CustomerClass::new(request_finish_dsrt, request_arrival_dsrt),
+            seed,
+        )
+    }
+
+    /// Same as [`System::from_seed`], but the first class of requests is
+    /// given as a whole, e.g. with its priority.
+    pub fn from_class(
+        nodes_number: usize,
+        queue_capacity: impl Into<QueueCapacity>,
+        class: CustomerClass,
+        seed: u64,
+    ) -> Self {
+        let mut seeder = R::seed_from_u64(seed);
+        let queue_capacity = queue_capacity.into();
+        let class = ClassSource::new(class, &mut seeder);
+        let discipline_rng = R::seed_from_u64(seeder.next_u64());
+        let balking_rng = R::seed_from_u64(seeder.next_u64());
+        let orbit_rng = R::seed_from_u64(seeder.next_u64());
+        let vacation_rng = R::seed_from_u64(seeder.next_u64());
+        let setup_rng = R::seed_from_u64(seeder.next_u64());
+        let breakdown_rng = R::seed_from_u64(seeder.next_u64());
+        let assignment_rng = R::seed_from_u64(seeder.next_u64());
+        let server_rng = R::seed_from_u64(seeder.next_u64());
+
+        let mut summary = Summary::new(
+            nodes_number,
+            queue_capacity.limit().map(|limit| nodes_number + limit),
+        );
+        summary.nodes = vec![NodeSummary::default(); nodes_number];
+
+        let mut system = Self {
+            current_tick: 0.0,
+            nodes: (0..nodes_number)
+                .map(|_| Node::new(Server::new()))
+                .collect(),
+            assignment: Assignment::default(),
+            last_assigned: None,
+            in_service: 0,
+            bulk_service: None,
+            groups: HashMap::new(),
+            vacation: None,
+            setup: None,
+            gates: HashMap::new(),
+            breakdowns: None,
+            suspended: HashMap::new(),
+            impatient: HashSet::new(),
+            events_queue: EventsQueue::new(),
+            queue: Box::<Fifo>::default(),
+            queue_capacity,
+            preemption: Preemption::None,
+            orbit: None,
+            orbit_size: 0,
+            batch_acceptance: BatchAcceptance::Partial,
+            pending_batches: HashMap::new(),
+            summary,
+            observers: Vec::new(),
+            nodes_number,
+            classes: Vec::new(),
+            started: false,
+            arrivals: true,
+            discipline_rng,
+            join_probabilities: Vec::new(),
+            balking_rng,
+            orbit_rng,
+            vacation_rng,
+            setup_rng,
+            breakdown_rng,
+            assignment_rng,
+            server_rng,
+            seeder,
+            next_request_id: 0,
+        };
+        system.push_class(class);
+
+        system
+    }
+
+    /// Adds class of requests with its own arrival process and service
+    /// times. Its index is the number of classes added before it.
+    ///
+    /// Should be called before the simulation is started.
+    pub fn with_class(mut self, class: CustomerClass) -> Self {
+        let class = ClassSource::new(class, &mut self.seeder);
+        self.push_class(class);
+        self
+    }
+
+    /// Splits statistics into consecutive intervals of `width` from the
+    /// start of simulation, see [`Summary::buckets`]. Completed requests are
+    /// counted in the interval they arrived in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is not positive.
+    pub fn with_buckets(mut self, width: f64) -> Self {
+        assert!(
+            width > 0.0,
+            "Width of buckets should be positive, got {}",
+            width
+        );
+
+        self.summary.bucket_width = Some(width);
+        for summary in &mut self.summary.classes {
+            summary.bucket_width = Some(width);
+        }
+        self
+    }
+
+    /// Makes requests enter the system only by [`System::receive`], e.g.
+    /// from other systems of a [`Network`](crate::network::Network). Arrival
+    /// processes of the classes are not used then.
+    pub fn without_arrivals(mut self) -> Self {
+        self.arrivals = false;
+        self
+    }
+
+    fn push_class(&mut self, class: ClassSource<R>) {
+        let summary = Summary {
+            bucket_width: self.summary.bucket_width,
+            ..Summary::new(self.summary.nodes_number, self.summary.max_in_system)
+        };
+
+        self.summary.classes.push(summary);
+        self.classes.push(class);
+    }
+
+    /// Replaces the queue discipline, which is [`Fifo`] by default.
+    ///
+    /// Should be called before the simulation is started, as requests
+    /// waiting in the previous queue are dropped.
+    pub fn with_discipline(mut self, discipline: impl QueueDiscipline + 'static) -> Self {
+        self.queue = Box::new(discipline);
+        self
+    }
+
+    /// Allows requests to interrupt service of requests with lower priority,
+    /// see [`Request::priority`]. Interrupted request is put into the queue,
+    /// so preemption happens only if there is a free place in it.
+    ///
+    /// Usually used together with [`Priority`](crate::discipline::Priority)
+    /// discipline, so that interrupted requests are served before requests
+    /// with lower priority.
+    pub fn with_preemption(mut self, preemption: Preemption) -> Self {
+        self.preemption = preemption;
+        self
+    }
+
+    /// Makes requests which find the system full join the `orbit` and try
+    /// again later instead of being lost.
+    pub fn with_orbit(mut self, orbit: Orbit) -> Self {
+        self.orbit = Some(orbit);
+        self
+    }
+
+    /// Makes nodes serve requests in groups of `min` to `max` requests with
+    /// one service time, the one of the first request in the group. A free
+    /// node takes a group as soon as there are `min` requests waiting.
+    ///
+    /// Requests which are served together are not interrupted by
+    /// preemption.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is zero or greater than `max`.
+    pub fn with_bulk_service(mut self, min: usize, max: usize) -> Self {
+        assert!(
+            0 < min && min <= max,
+            "Group sizes should satisfy 0 < min <= max, got {}..={}",
+            min,
+            max
+        );
+
+        let max_in_system = self
+            .queue_capacity
+            .limit()
+            .map(|limit| self.nodes_number * max + limit);
+        self.summary.max_in_system = max_in_system;
+        for summary in self.summary.classes.iter_mut() {
+            summary.max_in_system = max_in_system;
         }
+
+        self.bulk_service = Some(BulkService { min, max });
+        self
+    }
+
+    /// Makes nodes which have no requests to serve go on `vacation`. All
+    /// nodes start the simulation on vacation.
+    pub fn with_vacation(mut self, vacation: Vacation) -> Self {
+        self.vacation = Some(vacation);
+        self
+    }
+
+    /// Makes idle nodes spend time sampled from `setup` before they serve
+    /// waiting requests, including nodes returned from vacation. Nodes which
+    /// continue serving after a departure start immediately.
+    ///
+    /// Requests wait in the queue during setup, and are taken by the first
+    /// node ready to serve. Up to a group of requests per node in setup
+    /// waits in place of the node rather than in the queue, so with
+    /// [`QueueCapacity::Loss`] a request which wakes a node still waits for
+    /// its setup.
+    pub fn with_setup(mut self, setup: ConsumingDistribution) -> Self {
+        self.setup = Some(setup);
+        self
+    }
+
+    /// Makes nodes fail and get repaired according to `breakdowns`.
+    ///
+    /// Requests whose service waits for repair are counted as being in
+    /// service.
+    pub fn with_breakdowns(mut self, breakdowns: Breakdowns) -> Self {
+        self.breakdowns = Some(breakdowns);
+        self
+    }
+
+    /// Gives each node its own speed and service times, the `i`-th server
+    /// describes the node with index `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if number of servers differs from the number of nodes.
+    pub fn with_servers(mut self, servers: impl IntoIterator<Item = Server>) -> Self {
+        let nodes = servers.into_iter().map(Node::new).collect::<Vec<_>>();
+        assert_eq!(
+            nodes.len(),
+            self.nodes_number,
+            "Number of servers should be equal to the number of nodes"
+        );
+
+        self.nodes = nodes;
+        self
+    }
+
+    /// Sets how a node is chosen among idle ones, which is
+    /// [`Assignment::FastestFirst`] by default.
+    pub fn with_assignment(mut self, assignment: Assignment) -> Self {
+        self.assignment = assignment;
+        self
+    }
+
+    /// Sets how batches of requests are accepted, see
+    /// [`CustomerClass::with_batch`].
+    pub fn with_batch_acceptance(mut self, batch_acceptance: BatchAcceptance) -> Self {
+        self.batch_acceptance = batch_acceptance;
+        self
+    }
+
+    /// Makes requests which find all nodes busy and `k` requests waiting
+    /// join the queue with probability `join_probabilities[k]`, otherwise
+    /// they balk and leave. The last probability is used for longer queues.
+    ///
+    /// By default every request joins the queue if there is a place in it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the probabilities is not in \([0, 1]\).
+    pub fn with_balking(mut self, join_probabilities: impl Into<Vec<f64>>) -> Self {
+        let join_probabilities = join_probabilities.into();
+        assert!(
+            join_probabilities
+                .iter()
+                .all(|probability| (0.0..=1.0).contains(probability)),
+            "Join probabilities should be in [0, 1], got {:?}",
+            join_probabilities
+        );
+
+        self.join_probabilities = join_probabilities;
+        self
+    }
+
+    /// Registers `observer` which will be notified about events in the
+    /// system.
+    pub fn add_observer(&mut self, observer: impl Observer + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Same as [`System::add_observer`], but consumes and returns the system.
+    pub fn with_observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.add_observer(observer);
+        self
+    }
+
+    /// Current time of the simulation.
+    pub fn current_time(&self) -> f64 {
+        self.current_tick
+    }
+
+    /// Number of requests in the queue and in service.
+    pub fn requests_in_system(&self) -> usize {
+        self.queue.len() + self.in_service
+    }
+
+    /// Aggregated statistics from the start of simulation.
+    pub fn summary(&self) -> &Summary {
+        &self.summary
+    }
+
+    /// Time of the event which will be yielded by the next call of
+    /// [`Iterator::next`], infinity if there are no events, which is possible
+    /// only for the system [`without arrivals`](System::without_arrivals).
+    pub fn next_event_time(&mut self) -> f64 {
+        self.start();
+        self.skip_cancelled();
+
+        self.events_queue
+            .peek()
+            .map_or(f64::INFINITY, |event| event.time)
+    }
+
+    /// Lets in the request which left another system at `time`. A new
+    /// request of the same class is created with service time sampled from
+    /// the class of this system, and it keeps [`Request::entered_at`].
+    ///
+    /// Should be called in order of time, before events of this system
+    /// scheduled after `time` are processed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the system has no class of the request.
+    pub fn receive(&mut self, time: f64, request: &Request) -> SimulationEvent {
+        self.start();
+
+        assert!(
+            request.class < self.classes.len(),
+            "System has no class {} of the received request",
+            request.class
+        );
+        let received = Request {
+            entered_at: request.entered_at,
+            ..self.new_request(request.class)
+        };
+
+        self.process(Event {
+            time,
+            request: Some(received),
+            node: None,
+            r#type: EventType::Routed,
+        })
     }
 
-    pub fn next(&mut self) -> Stats {
-        self.finished_requests = None;
-        if self.events_queue.is_empty() {
-            self.produce_arrival();
+    /// Runs simulation until `stop` condition is met and returns statistics
+    /// from the start of simulation.
+    pub fn run(&mut self, stop: StopCondition) -> Summary {
+        let start = self.summary.clone();
+
+        match stop {
+            StopCondition::Time(horizon) => {
+                while self.next_event_time() <= horizon {
+                    self.step();
+                }
+                self.advance(self.current_tick.max(horizon));
+
+                self.summary.clone()
+            }
+            StopCondition::Events(events) => {
+                self.run_until_with(|summary| summary.events - start.events >= events)
+            }
+            StopCondition::Arrivals(arrivals) => {
+                self.run_until_with(|summary| summary.offered - start.offered >= arrivals)
+            }
+            StopCondition::Departures(departures) => {
+                self.run_until_with(|summary| summary.departures - start.departures >= departures)
+            }
+            StopCondition::Completed(completed) => {
+                self.run_until_with(|summary| summary.completed - start.completed >= completed)
+            }
         }
+    }
+
+    /// Runs simulation until time `horizon`. Events scheduled after it are
+    /// left unprocessed.
+    pub fn run_until(&mut self, horizon: f64) -> Summary {
+        self.run(StopCondition::Time(horizon))
+    }
+
+    /// Runs simulation for the given number of events.
+    pub fn run_for_events(&mut self, events: u64) -> Summary {
+        self.run(StopCondition::Events(events))
+    }
+
+    /// Runs simulation until `stop` returns `true`, or until there are no
+    /// events left. The condition is checked before each event.
+    pub fn run_until_with(&mut self, mut stop: impl FnMut(&Summary) -> bool) -> Summary {
+        while !stop(&self.summary) && self.step().is_some() {}
+
+        self.summary.clone()
+    }
 
-        let event = self
-            .events_queue
-            .pop()
-            .expect("Events queue should not be empty");
+    /// Processes the nearest event, `None` if there are no events.
+    fn step(&mut self) -> Option<SimulationEvent> {
+        self.start();
+        self.skip_cancelled();
 
-        self.handle_event(event);
+        let event = self.events_queue.pop()?;
 
-        if self.nodes_busy < self.nodes_number {
-            if let Some(mut request) = self.queue.pop_front() {
-                self.nodes_busy += 1;
-                request.started_at = Some(self.current_tick);
-                self.produce_departure(request);
+        Some(self.process(event))
+    }
+
+    /// Drops the nearest events which were cancelled after they had been
+    /// scheduled: departures of interrupted services and reneging of
+    /// requests which left the queue.
+    fn skip_cancelled(&mut self) {
+        while let Some(event) = self.events_queue.peek() {
+            let cancelled = match (event.r#type, event.request) {
+                (EventType::Departure, Some(request)) => {
+                    let node = request.node.expect("served request has node");
+                    self.nodes[node].departure != Some((event.time, request))
+                }
+                (EventType::Renege, Some(request)) => !self.impatient.contains(&request.id),
+                _ => false,
+            };
+            if !cancelled {
+                return;
             }
+
+            self.events_queue.pop();
         }
+    }
+
+    /// Handles `event` and describes the state of the system after it.
+    fn process(&mut self, event: Event) -> SimulationEvent {
+        let (request, refusal) = self.handle_event(&event);
+        self.summary.events += 1;
 
         log::debug!("Events: {:?}", self.events_queue);
         log::debug!("Queue: {:?}", self.queue);
 
-        let stats = Stats {
-            current_tick: self.current_tick,
-            requests_in_system: self.queue.len() + self.nodes_busy,
-            finished_request: self.finished_requests,
+        let event = SimulationEvent {
+            time: event.time,
+            r#type: event.r#type,
+            request,
+            blocked: refusal == Some(Refusal::Blocked),
+            orbited: refusal == Some(Refusal::Orbited),
+            balked: refusal == Some(Refusal::Balked),
+            queue_length: self.queue.len(),
+            node: event.node,
+            busy_nodes: self.nodes_in(NodeState::Busy),
+            in_service: self.in_service,
+            orbit_size: self.orbit_size,
+            down_nodes: self.nodes_in(NodeState::Down),
         };
 
-        log::debug!("Stats: {:?}", stats);
+        log::debug!("Event: {:?}", event);
 
-        stats
+        event
     }
 
-    fn handle_event(&mut self, event: Event) {
-        let Event {
-            time,
-            mut request,
-            r#type,
-        } = event;
-        self.current_tick = time;
+    /// Handles `event` and returns request it is related to, and why the
+    /// request was refused, if it was.
+    fn handle_event(&mut self, event: &Event) -> (Option<Request>, Option<Refusal>) {
+        self.advance(event.time);
+
+        match (event.request, event.node) {
+            (Some(request), _) => {
+                let (request, refusal) = self.handle_request_event(event.r#type, request);
+                (Some(request), refusal)
+            }
+            (None, Some(node)) => {
+                self.handle_node_event(event.r#type, node);
+                (None, None)
+            }
+            (None, None) => unreachable!("event is related to a request or a node"),
+        }
+    }
+
+    fn handle_node_event(&mut self, r#type: EventType, node: usize) {
+        let (min, _) = self.group_limits();
 
         match r#type {
-            EventType::Arrival => {
-                self.produce_arrival();
+            EventType::VacationEnd => {
+                self.set_state(node, NodeState::Idle);
 
-                if self.queue.capacity() == self.queue.len() {
-                    return;
+                let policy = self.vacation.as_ref().map(|vacation| vacation.policy);
+                if self.wakes() {
+                    self.wake_node(node);
+                } else if policy == Some(VacationPolicy::Multiple) {
+                    self.start_vacation(node);
                 }
+            }
+            EventType::SetupEnd => {
+                self.set_state(node, NodeState::Idle);
+
+                if self.queue.len() >= min {
+                    self.serve_next(node, self.open_gate(0));
+                } else if self.vacation.is_some() {
+                    self.start_vacation(node);
+                }
+            }
+            EventType::Failure => self.fail(node),
+            EventType::Repair => self.repair(node),
+            _ => unreachable!("{:?} event is related to a request", r#type),
+        }
+    }
+
+    fn handle_request_event(
+        &mut self,
+        r#type: EventType,
+        mut request: Request,
+    ) -> (Request, Option<Refusal>) {
+        let time = self.current_tick;
+        let class = request.class;
+
+        match r#type {
+            EventType::Arrival | EventType::Routed => {
                 request.created_at = Some(self.current_tick);
-                self.queue.push_back(request);
+                if let (EventType::Arrival, Some(phase)) = (r#type, self.classes[class].phase) {
+                    self.summary.classes[class].arrive_in_phase(phase);
+                }
+
+                let population = self.classes[class].class.population;
+                let batch = match r#type {
+                    EventType::Arrival if population.is_none() => {
+                        self.produce_arrival(class);
+                        request.entered_at = request.created_at;
+                        self.new_batch(request)
+                    }
+                    EventType::Arrival => {
+                        request.entered_at = request.created_at;
+                        vec![request]
+                    }
+                    _ => vec![request],
+                };
+                self.account(class, |summary| {
+                    summary.offered += batch.len() as u64;
+                    summary.batches += 1;
+                    if let Some(bucket) = summary.bucket_mut(time) {
+                        bucket.offered += batch.len() as u64;
+                    }
+                });
+                for request in &batch {
+                    self.notify(|observer| observer.on_arrival(time, request));
+                }
+
+                return (request, self.admit_batch(batch));
+            }
+            EventType::PhaseChange => {
+                let source = &mut self.classes[class];
+                if let Some(phase) = source.phase {
+                    source.phase = Some(source.class.arrival.jump(phase, &mut source.arrival_rng));
+                }
+                self.schedule_arrival(request);
+
+                return (request, None);
+            }
+            EventType::Retrial => {
+                self.orbit_size -= 1;
+                self.classes[class].orbiting -= 1;
+
+                request.retrials += 1;
+                self.account(class, |summary| summary.retrials += 1);
+
+                return (request, self.admit(request));
             }
             EventType::Departure => {
-                self.finished_requests = Some(request);
-                self.nodes_busy -= 1;
+                let node = request.node.expect("served request has node");
+                self.nodes[node].departure = None;
+                let gate = self.gates.remove(&request.id);
+                let group = self.groups.remove(&request.id).unwrap_or_default();
+                for request in std::iter::once(request).chain(group) {
+                    self.account(request.class, |summary| {
+                        summary.departures += 1;
+                        summary.complete(&request, time);
+                    });
+                    self.notify(|observer| observer.on_departure(time, &request));
+                    self.in_service -= 1;
+                    self.classes[request.class].busy -= 1;
+                    self.summary.nodes[node].completed += 1;
+                    self.release_source(request.class);
+                }
 
-                let Some(mut request) = self.queue.pop_front() else {
-                    return; // Skip if queue is empty
-                };
-                request.started_at = Some(self.current_tick);
+                self.release_node(node, gate);
+            }
+            EventType::Renege => {
+                self.impatient.remove(&request.id);
+                let request = self
+                    .queue
+                    .remove(request.id)
+                    .expect("reneging request is waiting");
+                self.classes[class].waiting -= 1;
+
+                if let Some(waiting) = self.pending_batches.get_mut(&request.batch) {
+                    *waiting -= 1;
+                    if *waiting == 0 {
+                        self.pending_batches.remove(&request.batch);
+                    }
+                }
+
+                self.account(class, |summary| summary.renege(&request, time));
+                self.notify(|observer| observer.on_renege(time, &request));
+                self.release_source(class);
 
-                self.nodes_busy += 1;
-                self.produce_departure(request);
+                return (request, None);
+            }
+            EventType::VacationEnd
+            | EventType::SetupEnd
+            | EventType::Failure
+            | EventType::Repair => {
+                unreachable!("{:?} event is related to a node", r#type)
             }
         }
+
+        (request, None)
     }
 
-    fn produce_arrival(&mut self) {
-        let request_arrival =
-            self.current_tick + self.request_arrival_dsrt.sample(&mut rand::thread_rng());
+    /// Creates the rest of the batch which arrived with `first` request.
+    fn new_batch(&mut self, first: Request) -> Vec<Request> {
+        let class = first.class;
+        let source = &mut self.classes[class];
+        let size = source.class.batch.sample(&mut source.batch_rng);
 
-        let request = self.new_request();
+        let mut batch = vec![first];
+        for _ in 1..size {
+            batch.push(Request {
+                batch: first.id,
+                created_at: first.created_at,
+                entered_at: first.entered_at,
+                ..self.new_request(class)
+            });
+        }
 
-        self.events_queue.push(Event {
-            time: request_arrival,
-            request,
-            r#type: EventType::Arrival,
-        });
+        batch
     }
 
-    fn new_request(&mut self) -> Request {
-        let time_to_finish = self.request_finish_dsrt.sample(&mut rand::thread_rng());
+    /// Lets arrived `batch` into the system according to the batch
+    /// acceptance, and returns why its first request was refused, if it
+    /// was.
+    ///
+    /// Waiting time of the batch is measured till the first of its requests
+    /// accepted on arrival starts service.
+    fn admit_batch(&mut self, batch: Vec<Request>) -> Option<Refusal> {
+        let id = batch[0].batch;
+        self.pending_batches.insert(id, 0);
 
-        Request::new(time_to_finish)
+        let whole = self.batch_acceptance == BatchAcceptance::Whole;
+        let refusals = if whole && !self.fits_batch(batch.len()) {
+            batch
+                .into_iter()
+                .map(|request| Some(self.block(request)))
+                .collect::<Vec<_>>()
+        } else {
+            batch
+                .into_iter()
+                .map(|request| self.admit(request))
+                .collect::<Vec<_>>()
+        };
+
+        if self.pending_batches.get(&id) == Some(&0) {
+            self.pending_batches.remove(&id);
+        }
+
+        refusals[0]
     }
 
-    fn produce_departure(&mut self, request: Request) {
-        self.events_queue.push(Event {
-            time: self.current_tick + request.time_to_finish,
-            request,
-            r#type: EventType::Departure,
-        });
+    /// Checks if all `size` requests of a batch can be served or wait.
+    fn fits_batch(&self, size: usize) -> bool {
+        let free_nodes = self.nodes_in(NodeState::Idle);
+
+        self.fits_waiting(size.saturating_sub(free_nodes))
+    }
+
+    /// Checks if `arriving` more requests can wait. Requests which will be
+    /// taken by nodes in setup take places of the nodes, not places in the
+    /// queue, so the system holds at most [`Summary::max_in_system`]
+    /// requests.
+    fn fits_waiting(&self, arriving: usize) -> bool {
+        let (_, max) = self.group_limits();
+        let reserved = self.nodes_in(NodeState::Setup) * max;
+
+        self.queue_capacity
+            .limit()
+            .is_none_or(|limit| self.queue.len() + arriving <= limit + reserved)
+    }
+
+    /// Checks if an arriving request would find neither a node to take it
+    /// nor a place to wait.
+    fn is_full(&self) -> bool {
+        let alone = self.bulk_service.is_none() && self.setup.is_none();
+        let takes =
+            self.nodes_in(NodeState::Idle) > 0 && (alone || self.wakes_with(self.queue.len() + 1));
+
+        !takes && !self.fits_waiting(1)
+    }
+
+    /// Lets arrived or retrying `request` into the system, and returns why
+    /// it was refused, if it was.
+    fn admit(&mut self, request: Request) -> Option<Refusal> {
+        let time = self.current_tick;
+        let class = request.class;
+
+        let idle = self.nodes_in(NodeState::Idle) > 0;
+        let alone = self.bulk_service.is_none();
+
+        let node = match idle {
+            true if alone && self.setup.is_none() => self.choose_node(),
+            false if alone => self.preempt(&request),
+            _ => None,
+        };
+
+        if let Some(node) = node {
+            self.account(class, |summary| summary.accepted += 1);
+            let gate = if idle { self.open_gate(1) } else { None };
+            self.start_group(vec![request], gate, node);
+        } else if idle && self.wakes_with(self.queue.len() + 1) {
+            self.account(class, |summary| summary.accepted += 1);
+            self.enqueue(request);
+            self.dispatch();
+        } else if !self.fits_waiting(1) {
+            return Some(self.block(request));
+        } else if self.balks() {
+            self.account(class, |summary| summary.balked += 1);
+            self.notify(|observer| observer.on_balk(time, &request));
+            self.release_source(class);
+            return Some(Refusal::Balked);
+        } else {
+            self.account(class, |summary| summary.accepted += 1);
+            self.enqueue(request);
+        }
+
+        None
+    }
+
+    /// Sends `request` which found the system full to the orbit, if it has
+    /// attempts left, otherwise the request is lost.
+    fn block(&mut self, request: Request) -> Refusal {
+        let time = self.current_tick;
+        let class = request.class;
+        let retried = request.retrials > 0;
+
+        if let Some(orbit) = &self.orbit {
+            if orbit
+                .max_retrials
+                .is_none_or(|max_retrials| request.retrials < max_retrials)
+            {
+                let delay = orbit.retrial.sample(&mut self.orbit_rng);
+                self.events_queue.push(Event {
+                    time: time + delay,
+                    request: Some(request),
+                    node: None,
+                    r#type: EventType::Retrial,
+                });
+                self.orbit_size += 1;
+                self.classes[class].orbiting += 1;
+
+                if !retried {
+                    self.account(class, |summary| summary.orbited += 1);
+                }
+                self.notify(|observer| observer.on_orbit(time, &request));
+
+                return Refusal::Orbited;
+            }
+        }
+
+        self.account(class, |summary| {
+            summary.lost += 1;
+            if retried {
+                summary.orbited_lost += 1;
+            }
+        });
+        self.notify(|observer| observer.on_block(time, &request));
+        self.release_source(class);
+
+        Refusal::Blocked
+    }
+
+    /// Decides whether arrived request refuses to join the queue of the
+    /// current length.
+    fn balks(&mut self) -> bool {
+        let Some(&probability) = self
+            .join_probabilities
+            .get(self.queue.len())
+            .or(self.join_probabilities.last())
+        else {
+            return false;
+        };
+
+        !self.balking_rng.gen_bool(probability)
+    }
+
+    /// Puts `request` into the queue and schedules its reneging if the
+    /// class requests are impatient.
+    fn enqueue(&mut self, request: Request) {
+        let source = &mut self.classes[request.class];
+        source.waiting += 1;
+
+        if let Some(waiting) = self.pending_batches.get_mut(&request.batch) {
+            *waiting += 1;
+        }
+
+        if let Some(patience) = &source.class.patience {
+            let patience = patience.sample(&mut source.patience_rng);
+            self.impatient.insert(request.id);
+            self.events_queue.push(Event {
+                time: self.current_tick + patience,
+                request: Some(request),
+                node: None,
+                r#type: EventType::Renege,
+            });
+        }
+
+        self.queue.push(request);
+    }
+
+    /// Interrupts service of the request with the lowest priority, if it is
+    /// lower than priority of `request`, and puts it into the queue. Among
+    /// requests with equal priority the one which would finish last is
+    /// chosen.
+    ///
+    /// Returns the node which was freed.
+    fn preempt(&mut self, request: &Request) -> Option<usize> {
+        if self.preemption == Preemption::None || !self.fits_waiting(1) {
+            return None;
+        }
+
+        let node = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(node, state)| state.departure.map(|departure| (node, departure)))
+            .filter(|(_, (_, victim))| victim.priority > request.priority)
+            .max_by(|(_, (a_time, a)), (_, (b_time, b))| {
+                (a.priority.cmp(&b.priority)).then(a_time.total_cmp(b_time))
+            })
+            .map(|(node, _)| node)?;
+
+        let (departure, mut victim) = self.nodes[node]
+            .departure
+            .take()
+            .expect("departure of the request in service is scheduled");
+
+        let speed = self.speed(victim.node);
+        victim.remaining_time = match self.preemption {
+            Preemption::Resume => Some((departure - self.current_tick) * speed),
+            Preemption::Repeat | Preemption::None => None,
+        };
+        victim.preemptions += 1;
+        let node = victim.node.take();
+
+        if let Some(gate) = self.gates.remove(&victim.id) {
+            self.gates.insert(request.id, gate);
+        }
+
+        let time = self.current_tick;
+        self.account(victim.class, |summary| summary.preempted += 1);
+        self.notify(|observer| observer.on_preemption(time, &victim));
+
+        self.in_service -= 1;
+        self.classes[victim.class].busy -= 1;
+        self.classes[victim.class].waiting += 1;
+        self.queue.push(victim);
+
+        node
+    }
+
+    /// Schedules the first arrival of each class.
+    fn start(&mut self) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+
+        if self.arrivals {
+            for class in 0..self.classes.len() {
+                let sources = self.classes[class].class.population.unwrap_or(1);
+                for _ in 0..sources {
+                    self.produce_arrival(class);
+                }
+            }
+        }
+        for node in 0..self.nodes_number {
+            if self.vacation.is_some() {
+                self.start_vacation(node);
+            }
+            if self.breakdowns.is_some() {
+                self.produce_failure(node);
+            }
+        }
+    }
+
+    fn produce_arrival(&mut self, class: usize) {
+        let request = self.new_request(class);
+        self.schedule_arrival(request);
+    }
+
+    /// Schedules arrival of `request`, or change of the phase before it for
+    /// arrivals which depend on a phase.
+    fn schedule_arrival(&mut self, request: Request) {
+        let source = &mut self.classes[request.class];
+        let phase = source.phase.unwrap_or_default();
+        let next = source
+            .class
+            .arrival
+            .sample(self.current_tick, phase, &mut source.arrival_rng);
+        let (delay, r#type) = match next {
+            NextArrival::Arrival(delay) => (delay, EventType::Arrival),
+            NextArrival::PhaseChange(delay) => (delay, EventType::PhaseChange),
+            NextArrival::Never => return,
+        };
+        if delay == f64::INFINITY {
+            return;
+        }
+
+        self.events_queue.push(Event {
+            time: self.current_tick + delay,
+            request: Some(request),
+            node: None,
+            r#type,
+        });
+    }
+
+    /// Lets the source of the request of `class` which left the system
+    /// think before sending the next one, if the class has finite
+    /// population.
+    fn release_source(&mut self, class: usize) {
+        if self.arrivals && self.classes[class].class.population.is_some() {
+            self.produce_arrival(class);
+        }
+    }
+
+    fn new_request(&mut self, class: usize) -> Request {
+        let source = &mut self.classes[class];
+        let time_to_finish = source.class.service.sample(&mut source.service_rng);
+
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+
+        Request {
+            class,
+            priority: source.class.priority,
+            ..Request::with_id(id, time_to_finish)
+        }
+    }
+
+    /// Minimum and maximum number of requests a node takes at once.
+    fn group_limits(&self) -> (usize, usize) {
+        self.bulk_service
+            .map_or((1, 1), |bulk| (bulk.min, bulk.max))
+    }
+
+    /// Number of nodes in `state`.
+    fn nodes_in(&self, state: NodeState) -> usize {
+        self.nodes.iter().filter(|node| node.state == state).count()
+    }
+
+    fn set_state(&mut self, node: usize, state: NodeState) {
+        self.nodes[node].state = state;
+        self.nodes[node].since = self.current_tick;
+    }
+
+    /// Chooses one of the nodes which are neither busy, on vacation, in setup
+    /// nor failed according to the assignment policy.
+    fn choose_node(&mut self) -> Option<usize> {
+        let mut idle = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.state == NodeState::Idle);
+
+        let chosen = match self.assignment {
+            Assignment::FastestFirst => idle
+                .rev()
+                .max_by(|(_, a), (_, b)| a.server.speed.total_cmp(&b.server.speed)),
+            Assignment::Random => {
+                let idle = idle.collect::<Vec<_>>();
+                if idle.is_empty() {
+                    None
+                } else {
+                    Some(idle[self.assignment_rng.gen_range(0..idle.len())])
+                }
+            }
+            Assignment::LongestIdle => idle.min_by(|(_, a), (_, b)| a.since.total_cmp(&b.since)),
+            Assignment::RoundRobin => {
+                let last = self.last_assigned;
+                idle.clone()
+                    .find(|(index, _)| last.is_none_or(|last| *index > last))
+                    .or_else(|| idle.next())
+            }
+        }
+        .map(|(index, _)| index);
+
+        if chosen.is_some() {
+            self.last_assigned = chosen;
+        }
+        chosen
+    }
+
+    /// Checks if there are enough waiting requests for one more node, besides
+    /// the ones which will be taken by nodes in setup.
+    fn wakes(&self) -> bool {
+        self.wakes_with(self.queue.len())
+    }
+
+    /// Same as [`System::wakes`], but for `waiting` requests in the queue.
+    fn wakes_with(&self, waiting: usize) -> bool {
+        let (min, max) = self.group_limits();
+
+        waiting >= min + self.nodes_in(NodeState::Setup) * max
+    }
+
+    /// Gives waiting requests to idle nodes, one by one or in groups.
+    fn dispatch(&mut self) {
+        while self.wakes() {
+            let Some(node) = self.choose_node() else {
+                break;
+            };
+            self.wake_node(node);
+        }
+    }
+
+    /// Makes idle `node` serve waiting requests, after setup if there is
+    /// one.
+    fn wake_node(&mut self, node: usize) {
+        let Some(setup) = &self.setup else {
+            self.serve_next(node, self.open_gate(0));
+            return;
+        };
+
+        let time = self.current_tick;
+        self.events_queue.push(Event {
+            time: time + setup.sample(&mut self.setup_rng),
+            request: None,
+            node: Some(node),
+            r#type: EventType::SetupEnd,
+        });
+        self.set_state(node, NodeState::Setup);
+
+        self.summary.setups += 1;
+        self.notify(|observer| observer.on_setup(time, node));
+    }
+
+    /// Lets `node` which finished service take next waiting requests, if
+    /// its gate allows. Otherwise the node goes on vacation, if there are
+    /// vacations, or becomes idle.
+    fn release_node(&mut self, node: usize, gate: Option<usize>) {
+        let (min, _) = self.group_limits();
+        self.set_state(node, NodeState::Idle);
+
+        if self.queue.len() >= min && gate != Some(0) {
+            self.serve_next(node, gate);
+        } else if self.vacation.is_some() {
+            self.start_vacation(node);
+        }
+
+        self.dispatch();
+    }
+
+    /// Sends `node` without work on vacation.
+    fn start_vacation(&mut self, node: usize) {
+        let vacation = self.vacation.as_ref().expect("vacations are enabled");
+
+        let time = self.current_tick;
+        self.events_queue.push(Event {
+            time: time + vacation.duration.sample(&mut self.vacation_rng),
+            request: None,
+            node: Some(node),
+            r#type: EventType::VacationEnd,
+        });
+        self.set_state(node, NodeState::Vacation);
+
+        self.summary.vacations += 1;
+        self.notify(|observer| observer.on_vacation(time, node));
+    }
+
+    /// Schedules the next failure of `node`.
+    fn produce_failure(&mut self, node: usize) {
+        let breakdowns = self.breakdowns.as_ref().expect("breakdowns are enabled");
+
+        self.events_queue.push(Event {
+            time: self.current_tick + breakdowns.uptime.sample(&mut self.breakdown_rng),
+            request: None,
+            node: Some(node),
+            r#type: EventType::Failure,
+        });
+    }
+
+    /// Breaks `node` down, interrupting what it does, and schedules its
+    /// repair.
+    fn fail(&mut self, node: usize) {
+        let breakdowns = self.breakdowns.as_ref().expect("breakdowns are enabled");
+        let time = self.current_tick;
+
+        self.events_queue.push(Event {
+            time: time + breakdowns.repair.sample(&mut self.breakdown_rng),
+            request: None,
+            node: Some(node),
+            r#type: EventType::Repair,
+        });
+
+        let state = self.nodes[node].state;
+        self.set_state(node, NodeState::Down);
+        match state {
+            NodeState::Idle => {}
+            NodeState::Busy => self.interrupt(node),
+            NodeState::Vacation => {
+                self.events_queue
+                    .remove(|event| event.is_of_node(EventType::VacationEnd, node));
+            }
+            NodeState::Setup => {
+                self.events_queue
+                    .remove(|event| event.is_of_node(EventType::SetupEnd, node));
+            }
+            NodeState::Down => unreachable!("failed node does not fail again"),
+        }
+
+        self.summary.failures += 1;
+        self.notify(|observer| observer.on_failure(time, node));
+
+        self.dispatch();
+    }
+
+    /// Interrupts service of requests at failed `node`.
+    fn interrupt(&mut self, node: usize) {
+        let interruption = self
+            .breakdowns
+            .as_ref()
+            .map_or(Interruption::default(), |breakdowns| {
+                breakdowns.interruption
+            });
+        let time = self.current_tick;
+
+        let (departure, mut leader) = self.nodes[node]
+            .departure
+            .take()
+            .expect("departure of the request at busy node is scheduled");
+
+        if interruption == Interruption::Resume {
+            leader.remaining_time = Some((departure - time) * self.nodes[node].server.speed);
+        }
+
+        let group = match interruption {
+            Interruption::Resume => self.groups.get(&leader.id).cloned().unwrap_or_default(),
+            Interruption::Requeue | Interruption::Lose => {
+                self.gates.remove(&leader.id);
+                self.groups.remove(&leader.id).unwrap_or_default()
+            }
+        };
+        for request in std::iter::once(leader).chain(group) {
+            self.account(request.class, |summary| summary.interrupted += 1);
+            self.notify(|observer| observer.on_interruption(time, &request));
+        }
+
+        match interruption {
+            Interruption::Resume => {
+                leader.interrupted_at = Some(time);
+                if let Some(group) = self.groups.get_mut(&leader.id) {
+                    for request in group.iter_mut() {
+                        request.interrupted_at = Some(time);
+                    }
+                }
+                self.suspended.insert(node, leader);
+            }
+            Interruption::Requeue | Interruption::Lose => {
+                let group = self.groups.remove(&leader.id).unwrap_or_default();
+                for mut request in std::iter::once(leader).chain(group) {
+                    self.in_service -= 1;
+                    self.classes[request.class].busy -= 1;
+
+                    if interruption == Interruption::Lose || !self.fits_waiting(1) {
+                        self.account(request.class, |summary| summary.interrupted_lost += 1);
+                        self.release_source(request.class);
+                        continue;
+                    }
+
+                    request.node = None;
+                    request.remaining_time = None;
+                    request.interrupted_at = Some(time);
+
+                    self.classes[request.class].waiting += 1;
+                    self.queue.push(request);
+                }
+            }
+        }
+    }
+
+    /// Returns repaired `node` to work: it continues the suspended service,
+    /// or behaves as a node which has just become free.
+    fn repair(&mut self, node: usize) {
+        let time = self.current_tick;
+        self.produce_failure(node);
+        self.notify(|observer| observer.on_repair(time, node));
+
+        if let Some(mut leader) = self.suspended.remove(&node) {
+            resume(&mut leader, time);
+            if let Some(group) = self.groups.get_mut(&leader.id) {
+                for request in group.iter_mut() {
+                    resume(request, time);
+                }
+            }
+
+            self.set_state(node, NodeState::Busy);
+            self.produce_departure(leader);
+            return;
+        }
+
+        self.set_state(node, NodeState::Idle);
+        if self.vacation.is_some() && !self.wakes() {
+            self.start_vacation(node);
+        }
+
+        self.dispatch();
+    }
+
+    /// Number of requests a node starting to serve may take before its next
+    /// vacation, `None` if the service is exhaustive. Includes `arriving`
+    /// requests which are not in the queue yet.
+    fn open_gate(&self, arriving: usize) -> Option<usize> {
+        self.vacation
+            .as_ref()
+            .filter(|vacation| vacation.service == VacationService::Gated)
+            .map(|_| self.queue.len() + arriving)
+    }
+
+    /// Takes a group of waiting requests for `node`, which may take `gate`
+    /// requests in total before its next vacation.
+    fn serve_next(&mut self, node: usize, gate: Option<usize>) {
+        let (_, max) = self.group_limits();
+
+        let group = (0..max)
+            .map_while(|_| self.queue.pop(&mut self.discipline_rng))
+            .collect::<Vec<_>>();
+        for request in &group {
+            self.classes[request.class].waiting -= 1;
+        }
+
+        self.start_group(group, gate, node);
+    }
+
+    /// Takes `node` for `group` of requests, which are served till the
+    /// departure of the first one. The node may take `gate` requests in
+    /// total, including the group, before its next vacation.
+    fn start_group(&mut self, group: Vec<Request>, gate: Option<usize>, node: usize) {
+        self.summary.take_group(group.len());
+        if let Some(gate) = gate {
+            self.gates
+                .insert(group[0].id, gate.saturating_sub(group.len()));
+        }
+
+        let mut group = group
+            .into_iter()
+            .map(|request| self.begin_service(request, node))
+            .collect::<Vec<_>>();
+        let leader = group.remove(0);
+        if !group.is_empty() {
+            self.groups.insert(leader.id, group);
+        }
+
+        self.set_state(node, NodeState::Busy);
+        self.produce_departure(leader);
+    }
+
+    /// Marks `request` as being served by `node`. Time of the first service
+    /// start is kept for interrupted requests, so waiting time is measured
+    /// till it.
+    fn begin_service(&mut self, mut request: Request, node: usize) -> Request {
+        self.impatient.remove(&request.id);
+
+        let time = self.current_tick;
+        if request.started_at.is_none() {
+            request.started_at = Some(time);
+
+            if self.pending_batches.remove(&request.batch).is_some() {
+                let created_at = request.created_at.expect("started request was created");
+                self.account(request.class, |summary| {
+                    summary.batches_started += 1;
+                    summary.batch_waiting_time_sum += time - created_at;
+                });
+            }
+        }
+        resume(&mut request, time);
+        request.node = Some(node);
+        self.notify(|observer| observer.on_service_start(time, &request));
+
+        self.in_service += 1;
+        self.classes[request.class].busy += 1;
+
+        request
+    }
+
+    /// Speed of `node`, 1 for requests which are not served.
+    fn speed(&self, node: Option<usize>) -> f64 {
+        node.map_or(1.0, |node| self.nodes[node].server.speed)
+    }
+
+    /// Schedules departure of `request` from its node. Service time at the
+    /// node is divided by its speed.
+    fn produce_departure(&mut self, request: Request) {
+        let node = &self.nodes[request.node.expect("served request has node")];
+        let service = match (&node.server.service, request.remaining_time) {
+            (Some(service), None) => service.sample(&mut self.server_rng),
+            _ => request.time_left(),
+        };
+
+        let time = self.current_tick + service / node.server.speed;
+        self.nodes[request.node.expect("served request has node")].departure =
+            Some((time, request));
+        self.events_queue.push(Event {
+            time,
+            request: Some(request),
+            node: None,
+            r#type: EventType::Departure,
+        });
+    }
+
+    /// Moves the clock to `time` and accounts the time spent in the current
+    /// state.
+    fn advance(&mut self, time: f64) {
+        let full = self.is_full();
+        self.summary.advance_nodes(
+            time,
+            self.nodes_in(NodeState::Vacation),
+            self.nodes_in(NodeState::Setup),
+            self.nodes_in(NodeState::Down),
+            self.nodes.iter().map(|node| node.state == NodeState::Busy),
+        );
+        self.summary.advance(
+            time,
+            self.queue.len(),
+            self.nodes_in(NodeState::Busy),
+            self.in_service,
+            self.orbit_size,
+            full,
+        );
+        let busy_nodes = self.busy_nodes_by_class();
+        for ((summary, source), busy_nodes) in self
+            .summary
+            .classes
+            .iter_mut()
+            .zip(&self.classes)
+            .zip(busy_nodes)
+        {
+            if let Some(phase) = source.phase {
+                summary.advance_phase(time, phase);
+            }
+            summary.advance(
+                time,
+                source.waiting,
+                busy_nodes,
+                source.busy,
+                source.orbiting,
+                full,
+            );
+        }
+        self.current_tick = time;
+    }
+
+    /// Number of busy nodes serving requests of each class. A node serving
+    /// a group is counted once for every class in the group.
+    fn busy_nodes_by_class(&self) -> Vec<usize> {
+        let mut busy_nodes = vec![0; self.classes.len()];
+
+        for node in &self.nodes {
+            let Some((_, leader)) = node.departure.filter(|_| node.state == NodeState::Busy) else {
+                continue;
+            };
+            let group = self.groups.get(&leader.id).into_iter().flatten();
+
+            let mut classes = std::iter::once(leader.class)
+                .chain(group.map(|request| request.class))
+                .collect::<Vec<_>>();
+            classes.sort_unstable();
+            classes.dedup();
+            for class in classes {
+                busy_nodes[class] += 1;
+            }
+        }
+
+        busy_nodes
+    }
+
+    /// Applies `update` to the aggregated statistics and to the statistics
+    /// of `class`.
+    fn account(&mut self, class: usize, mut update: impl FnMut(&mut Summary)) {
+        update(&mut self.summary);
+        update(&mut self.summary.classes[class]);
+    }
+
+    fn notify(&mut self, mut callback: impl FnMut(&mut dyn Observer)) {
+        for observer in self.observers.iter_mut() {
+            callback(observer.as_mut());
+        }
+    }
+}
+
+/// Accounts the time `request` waited since its service was interrupted by
+/// failure of a node, if it was.
+fn resume(request: &mut Request, time: f64) {
+    if let Some(interrupted_at) = request.interrupted_at.take() {
+        request.failure_delay += time - interrupted_at;
+    }
+}
+
+impl<R: RngCore + SeedableRng> Iterator for System<R> {
+    type Item = SimulationEvent;
+
+    /// Processes the nearest event. The iterator ends only if there are no
+    /// events, which happens to systems [`System::without_arrivals`] once
+    /// received requests are served.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::discipline::{Lifo, Priority, ShortestJobFirst};
+    use crate::distributions::{BatchDistribution, Mmpp, ProducingDistribution};
+
+    fn system<R: RngCore + SeedableRng>(seed: u64) -> System<R> {
+        System::from_seed(
+            3,
+            8,
+            ConsumingDistribution::Exponential { λ: 1.0 / 50.0 },
+            ProducingDistribution::Exponential { λ: 1.0 / 60.0 },
+            seed,
+        )
+    }
+
+    fn trajectory<R: RngCore + SeedableRng>(system: &mut System<R>) -> Vec<(f64, usize)> {
+        system
+            .take(1000)
+            .map(|event| (event.time, event.requests_in_system()))
+            .collect()
+    }
+
+    #[test]
+    fn test_same_seed_same_trajectory() {
+        let first = trajectory(&mut system::<StdRng>(42));
+        let second = trajectory(&mut system::<StdRng>(42));
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_different_seeds_different_trajectories() {
+        let first = trajectory(&mut system::<StdRng>(1));
+        let second = trajectory(&mut system::<StdRng>(2));
+
+        assert_ne!(first, second);
+    }
+
+    /// Arrivals every tick, each request is served for 10 ticks, so the
+    /// system is filled after `nodes_number + queue_capacity` arrivals.
+    #[test]
+    fn test_queue_capacity_is_enforced() {
+        let mut system = System::new(
+            2,
+            3,
+            ConsumingDistribution::Degenerate { μ: 0.1 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        );
+
+        for tick in 1..=5 {
+            let event = system.next().unwrap();
+            assert_eq!(event.time, tick as f64);
+            assert_eq!(event.requests_in_system(), tick);
+            assert!(!event.blocked);
+        }
+        assert_eq!(system.queue.len(), 3);
+        assert_eq!(system.nodes_in(NodeState::Busy), 2);
+
+        for tick in 6..=10 {
+            let event = system.next().unwrap();
+            assert_eq!(event.time, tick as f64);
+            assert_eq!(event.requests_in_system(), 5);
+            assert!(event.blocked);
+        }
+
+        let event = system.next().unwrap();
+        assert_eq!(event.time, 11.0, "first departure processed");
+        assert_eq!(event.r#type, EventType::Departure);
+
+        let summary = system.summary();
+        assert_eq!(summary.offered, 10);
+        assert_eq!(summary.accepted, 5);
+        assert_eq!(summary.lost, 5);
+        assert_eq!(summary.loss_probability(), 0.5);
+    }
+
+    #[test]
+    fn test_pure_loss() {
+        let mut system = System::new(
+            2,
+            QueueCapacity::Loss,
+            ConsumingDistribution::Degenerate { μ: 0.1 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        );
+
+        let event = system.nth(10).unwrap();
+        assert_eq!(event.time, 11.0, "first departure processed");
+
+        let summary = system.summary();
+        assert_eq!(summary.offered, 10);
+        assert_eq!(summary.accepted, 2);
+        assert_eq!(summary.lost, 8);
+        assert!(system.queue.is_empty());
+    }
+
+    #[test]
+    fn test_unbounded_queue() {
+        let mut system = System::new(
+            2,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 0.1 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        );
+
+        assert!(system.by_ref().take(100).all(|event| !event.blocked));
+
+        let summary = system.summary();
+        assert_eq!(summary.lost, 0);
+        assert_eq!(summary.accepted, summary.offered);
+        assert!(system.queue.len() > 50);
+    }
+
+    fn degenerate_system() -> System {
+        System::new(
+            2,
+            3,
+            ConsumingDistribution::Degenerate { μ: 0.1 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        )
+    }
+
+    #[test]
+    fn test_run_until_stops_at_horizon() {
+        let mut system = degenerate_system();
+
+        let summary = system.run_until(10.5);
+        assert_eq!(summary.time, 10.5);
+        assert_eq!(summary.offered, 10);
+        assert_eq!(summary.completed, 0);
+        assert_eq!(system.current_time(), 10.5);
+
+        let summary = system.run_until(12.0);
+        assert_eq!(summary.offered, 12);
+        assert_eq!(summary.completed, 2, "departures at 11 and 12");
+        assert_eq!(summary.waiting_mean(), 0.0);
+        assert_eq!(summary.sojourn_mean(), 10.0);
+    }
+
+    #[test]
+    fn test_run_with_counters() {
+        let mut system = degenerate_system();
+
+        let summary = system.run(StopCondition::Arrivals(3));
+        assert_eq!(summary.offered, 3);
+        assert_eq!(summary.time, 3.0);
+
+        let summary = system.run(StopCondition::Completed(3));
+        assert_eq!(summary.completed, 3);
+        assert_eq!(summary.departures, 3);
+
+        let events = summary.events;
+        let summary = system.run_for_events(5);
+        assert_eq!(summary.events, events + 5);
+
+        let lost = summary.lost;
+        let summary = system.run_until_with(|summary| summary.lost == lost + 2);
+        assert_eq!(summary.lost, lost + 2);
+    }
+
+    #[test]
+    fn test_time_weighted_means() {
+        let mut system = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 0.5 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        );
+
+        // Node is busy since 1, one request waits during [2, 4] and two
+        // during [4, 5].
+        let summary = system.run_until(5.0);
+        assert_eq!(summary.mean_busy_nodes(), 0.8);
+        assert_eq!(summary.mean_queue_length(), 0.8);
+        assert_eq!(summary.mean_in_system(), 1.6);
+    }
+
+    #[test]
+    fn test_state_probabilities() {
+        let mut system = System::new(
+            1,
+            1,
+            ConsumingDistribution::Degenerate { μ: 0.5 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        );
+
+        // Empty during [0, 1], one request during [1, 2], and then the system
+        // is full: arrivals at 3, 5, ... wait for the node, while arrivals at
+        // 4, 6, ... are lost.
+        let summary = system.run_until(10.0);
+        assert_eq!(summary.state_probabilities(), vec![0.1, 0.1, 0.8]);
+        assert_eq!(summary.blocking_probability(), 0.8);
+        assert_eq!(summary.waiting_probability(), 0.1);
+        assert_eq!(summary.lost, 4);
+    }
+
+    fn loaded_system() -> System {
+        System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Exponential { λ: 1.0 / 0.9 },
+            ProducingDistribution::Exponential { λ: 1.0 },
+            3,
+        )
+    }
+
+    #[test]
+    fn test_queue_disciplines() {
+        let horizon = 100_000.0;
+        let fifo = loaded_system().run_until(horizon);
+        let lifo = loaded_system()
+            .with_discipline(Lifo::default())
+            .run_until(horizon);
+        let sjf = loaded_system()
+            .with_discipline(ShortestJobFirst::default())
+            .run_until(horizon);
+
+        // Mean waiting time does not depend on the order of service if it
+        // does not use service times, but its variance does.
+        assert_eq!(fifo.offered, lifo.offered);
+        assert!((fifo.waiting_mean() - lifo.waiting_mean()).abs() < 0.1 * fifo.waiting_mean());
+        assert!(lifo.waiting_variance() > 2.0 * fifo.waiting_variance());
+
+        assert!(sjf.waiting_mean() < 0.5 * fifo.waiting_mean());
+    }
+
+    #[test]
+    fn test_customer_classes() {
+        let mut system = System::new(
+            1,
+            QueueCapacity::Loss,
+            ConsumingDistribution::Degenerate { μ: 2.0 },
+            ProducingDistribution::Degenerate { value: 2 },
+            0,
+        )
+        .with_class(CustomerClass::new(
+            ConsumingDistribution::Degenerate { μ: 0.5 },
+            ProducingDistribution::Degenerate { value: 5 },
+        ));
+
+        // First class arrives at 2, 4, 6, 8 and is served for 0.5, second one
+        // arrives at 5 and takes the node till 7, so arrival at 6 is lost.
+        let events = system.by_ref().take(5).collect::<Vec<_>>();
+        assert!(events[..4]
+            .iter()
+            .all(|event| event.request.map(|request| request.class) == Some(0)));
+        assert_eq!(events[4].request.map(|request| request.class), Some(1));
+        assert_eq!(events[4].time, 5.0);
+
+        let summary = system.run_until(9.5);
+        assert_eq!(summary.offered, 5);
+        assert_eq!(summary.lost, 1);
+        assert_eq!(summary.classes.len(), 2);
+        assert_eq!(summary.classes[0].offered, 4);
+        assert_eq!(summary.classes[0].lost, 1);
+        assert_eq!(summary.classes[1].offered, 1);
+        assert_eq!(summary.classes[1].lost, 0);
+        assert_eq!(summary.classes[1].sojourn_mean(), 2.0);
+
+        assert_eq!(summary.classes[0].mean_busy_nodes(), 1.5 / 9.5);
+        assert_eq!(summary.classes[1].mean_busy_nodes(), 2.0 / 9.5);
+        assert_eq!(summary.mean_busy_nodes(), 3.5 / 9.5);
+    }
+
+    #[test]
+    fn test_iterator_adapters() {
+        let mut system = degenerate_system();
+
+        let departures = system
+            .by_ref()
+            .take_while(|event| event.time <= 30.0)
+            .filter(|event| event.r#type == EventType::Departure)
+            .collect::<Vec<_>>();
+
+        assert_eq!(departures.len(), 4, "two departures every 10 ticks");
+        assert!(departures.iter().all(|event| event.busy_nodes == 2));
+        assert_eq!(
+            departures[2].request.map(|request| request.id),
+            Some(2),
+            "third request was queued"
+        );
+        assert_eq!(
+            departures[2].request.and_then(|request| request.started_at),
+            Some(11.0)
+        );
+    }
+
+    /// High priority requests arrive at 12 and are served for 1 tick, low
+    /// priority ones arrive at 10 and 20 and are served for 4 ticks.
+    fn priority_system() -> System {
+        System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 1.0 },
+            ProducingDistribution::Degenerate { value: 12 },
+            0,
+        )
+        .with_class(
+            CustomerClass::new(
+                ConsumingDistribution::Degenerate { μ: 0.25 },
+                ProducingDistribution::Degenerate { value: 10 },
+            )
+            .with_priority(1),
+        )
+        .with_discipline(Priority::default())
+    }
+
+    #[test]
+    fn test_priorities() {
+        let summary = priority_system().run_until(19.0);
+        assert_eq!(summary.preempted, 0);
+        assert_eq!(summary.classes[0].waiting_mean(), 2.0);
+        assert_eq!(summary.classes[1].sojourn_mean(), 4.0);
+
+        // Low priority request is interrupted at 12 with 2 ticks of service
+        // left, and returns to the node at 13.
+        let summary = priority_system()
+            .with_preemption(Preemption::Resume)
+            .run_until(19.0);
+        assert_eq!(summary.preempted, 1);
+        assert_eq!(summary.classes[1].preempted, 1);
+        assert_eq!(summary.classes[0].sojourn_mean(), 1.0);
+        assert_eq!(summary.classes[1].waiting_mean(), 0.0);
+        assert_eq!(summary.classes[1].sojourn_mean(), 5.0);
+
+        let summary = priority_system()
+            .with_preemption(Preemption::Repeat)
+            .run_until(19.0);
+        assert_eq!(summary.preempted, 1);
+        assert_eq!(summary.classes[1].sojourn_mean(), 7.0);
+        assert_eq!(summary.mean_busy_nodes(), 7.0 / 19.0);
+    }
+
+    #[test]
+    fn test_balking() {
+        let summary = degenerate_system().with_balking([1.0, 0.0]).run_until(10.5);
+
+        // Two requests are served, one waits and the rest refuse to join
+        // the queue of one request.
+        assert_eq!(summary.offered, 10);
+        assert_eq!(summary.accepted, 3);
+        assert_eq!(summary.balked, 7);
+        assert_eq!(summary.lost, 0);
+        assert_eq!(summary.balking_probability(), 0.7);
+        assert_eq!(summary.mean_queue_length(), 7.5 / 10.5);
+    }
+
+    #[test]
+    fn test_reneging() {
+        let mut system = <System>::from_class(
+            1,
+            QueueCapacity::Unbounded,
+            CustomerClass::new(
+                ConsumingDistribution::Degenerate { μ: 0.2 },
+                ProducingDistribution::Degenerate { value: 3 },
+            )
+            .with_patience(ConsumingDistribution::Degenerate { μ: 0.4 }),
+            0,
+        );
+
+        // Requests arrive every 3 ticks, are served for 5 and wait at most
+        // 2.5: the one arrived at 6 is taken at 8, the one arrived at 9
+        // leaves at 11.5, and the one arrived at 12 is taken at 13.
+        let events = system.by_ref().take(8).collect::<Vec<_>>();
+        assert_eq!(events.last().map(|event| event.time), Some(15.0));
+
+        let reneges = events
+            .iter()
+            .filter(|event| event.r#type == EventType::Renege)
+            .map(|event| (event.time, event.request.map(|request| request.id)))
+            .collect::<Vec<_>>();
+        assert_eq!(reneges, vec![(11.5, Some(2))]);
+
+        let summary = system.summary();
+        assert_eq!(summary.offered, 5);
+        assert_eq!(summary.accepted, 5);
+        assert_eq!(summary.completed, 2);
+        assert_eq!(summary.waiting_mean(), 1.0);
+        assert_eq!(summary.reneged, 1);
+        assert_eq!(summary.reneged_waiting_mean(), 2.5);
+        assert_eq!(summary.abandonment_probability(), 0.2);
+    }
+
+    #[test]
+    fn test_retrial_orbit() {
+        let mut system = System::new(
+            1,
+            QueueCapacity::Loss,
+            ConsumingDistribution::Degenerate { μ: 0.25 },
+            ProducingDistribution::Degenerate { value: 3 },
+            0,
+        )
+        .with_orbit(Orbit::new(ConsumingDistribution::Degenerate { μ: 0.4 }).with_max_retrials(1));
+
+        // Requests arrive every 3 ticks and are served for 4, blocked ones
+        // retry once after 2.5 ticks: the one arrived at 6 is taken at 8.5,
+        // while the one arrived at 9 is lost at 11.5.
+        let events = system.by_ref().take(7).collect::<Vec<_>>();
+        let retrials = events
+            .iter()
+            .filter(|event| event.r#type == EventType::Retrial)
+            .map(|event| {
+                let id = event.request.map(|request| request.id);
+                (event.time, id, event.blocked)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(retrials, vec![(8.5, Some(1), false), (11.5, Some(2), true)]);
+        assert!(events[6].orbited);
+        assert_eq!(events[6].orbit_size, 1);
+
+        let summary = system.run_until(13.0);
+        assert_eq!(summary.offered, 4);
+        assert_eq!(summary.accepted, 2);
+        assert_eq!(summary.lost, 1);
+        assert_eq!(summary.orbited, 3);
+        assert_eq!(summary.retrials, 2);
+        assert_eq!(summary.orbited_completed, 1);
+        assert_eq!(summary.orbited_lost, 1);
+        assert_eq!(summary.waiting_mean(), 1.25);
+        assert_eq!(summary.mean_orbit_size(), 6.0 / 13.0);
+    }
+
+    fn batch_system(
+        queue_capacity: impl Into<QueueCapacity>,
+        size: usize,
+        every: u64,
+        μ: f64,
+    ) -> System {
+        System::from_class(
+            1,
+            queue_capacity,
+            CustomerClass::new(
+                ConsumingDistribution::Degenerate { μ },
+                ProducingDistribution::Degenerate { value: every },
+            )
+            .with_batch(BatchDistribution::Constant { size }),
+            0,
+        )
+    }
+
+    #[test]
+    fn test_batch_acceptance() {
+        // Batches of 3 arrive every 2 ticks and are served for 5, so at 4
+        // and 8 there is place for one request only.
+        let summary = batch_system(3, 3, 2, 0.2).run_until(11.0);
+        assert_eq!(summary.offered, 15);
+        assert_eq!(summary.batches, 5);
+        assert_eq!(summary.batch_size_mean(), 3.0);
+        assert_eq!(summary.accepted, 5);
+        assert_eq!(summary.lost, 10);
+
+        let summary = batch_system(3, 3, 2, 0.2)
+            .with_batch_acceptance(BatchAcceptance::Whole)
+            .run_until(11.0);
+        assert_eq!(summary.offered, 15);
+        assert_eq!(summary.accepted, 3);
+        assert_eq!(summary.lost, 12);
+    }
+
+    #[test]
+    fn test_batch_waiting() {
+        // Pairs arrive every 3 ticks and are served for 4: the first pair
+        // starts at 3, the second one waits for the node from 6 till 11.
+        let mut system = batch_system(QueueCapacity::Unbounded, 2, 3, 0.25);
+
+        let summary = system.run_until(14.0);
+        assert_eq!(summary.batches, 4);
+        assert_eq!(summary.batches_started, 2);
+        assert_eq!(summary.batch_waiting_mean(), 2.5);
+        assert_eq!(summary.completed, 2);
+        assert_eq!(summary.waiting_mean(), 2.0);
+    }
+
+    #[test]
+    fn test_bulk_service() {
+        let mut system = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 1.0 / 4.5 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        )
+        .with_bulk_service(2, 3);
+
+        // The node waits for the second request at 2, and then takes three
+        // of the four waiting requests at 6.5.
+        let event = system.next().unwrap();
+        assert_eq!((event.queue_length, event.busy_nodes), (1, 0));
+        let event = system.next().unwrap();
+        assert_eq!((event.queue_length, event.in_service), (0, 2));
+        assert_eq!(event.requests_in_system(), 2);
+
+        let summary = system.run_until(10.5);
+        assert_eq!(summary.group_sizes, vec![0, 0, 1, 1]);
+        assert_eq!(summary.group_size_distribution(), vec![0.0, 0.0, 0.5, 0.5]);
+        assert_eq!(summary.group_size_mean(), 2.5);
+        assert_eq!(summary.completed, 2);
+        assert_eq!(summary.mean_busy_nodes(), 8.5 / 10.5);
+        assert_eq!(summary.mean_in_service(), 2.0);
+        assert_eq!(summary.mean_in_system(), 4.0);
+        assert_eq!(summary.classes[0].mean_busy_nodes(), 8.5 / 10.5);
+        assert_eq!(summary.classes[0].mean_in_service(), 2.0);
+    }
+
+    #[test]
+    fn test_bulk_blocking() {
+        let mut system = System::new(
+            1,
+            QueueCapacity::Finite(1),
+            ConsumingDistribution::Degenerate { μ: 1.0 / 4.5 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        )
+        .with_bulk_service(2, 3);
+
+        // Pairs are served from 2 till 6.5 and from 7 till 11.5, and the
+        // system is full once a request waits for the busy node.
+        let summary = system.run_until(10.0);
+        assert_eq!(summary.lost, 5);
+        assert_eq!(summary.blocking_probability(), 5.5 / 10.0);
+    }
+
+    fn vacation_system(service: VacationService) -> System {
+        System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 0.8 },
+            ProducingDistribution::Degenerate { value: 2 },
+            0,
+        )
+        .with_vacation(
+            Vacation::new(ConsumingDistribution::Degenerate { μ: 1.0 / 4.5 }).with_service(service),
+        )
+    }
+
+    #[test]
+    fn test_vacations() {
+        // Requests arrive every 2 ticks and are served for 1.25, the node
+        // returns from the first vacation at 4.5 and serves till 9.5.
+        let summary = vacation_system(VacationService::Exhaustive).run_until(13.0);
+        assert_eq!(summary.completed, 4);
+        assert_eq!(summary.vacations, 2);
+        assert_eq!(summary.mean_vacation_nodes(), 8.0 / 13.0);
+
+        // The node takes only two requests waiting at 4.5, and the one
+        // arrived at 6 waits till the next return at 11.5.
+        let summary = vacation_system(VacationService::Gated).run_until(13.0);
+        assert_eq!(summary.completed, 3);
+        assert_eq!(summary.vacations, 2);
+        assert_eq!(summary.waiting_mean(), 3.25);
+    }
+
+    #[test]
+    fn test_setup() {
+        let mut system = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 1.0 },
+            ProducingDistribution::Degenerate { value: 3 },
+            0,
+        )
+        .with_setup(ConsumingDistribution::Degenerate { μ: 2.0 });
+
+        let events = system.by_ref().take(2).collect::<Vec<_>>();
+        assert_eq!(events[1].r#type, EventType::SetupEnd);
+        assert_eq!(events[1].time, 3.5);
+        assert_eq!(events[1].request, None);
+
+        // Every request finds the node idle and waits for its setup.
+        let summary = system.run_until(10.0);
+        assert_eq!(summary.setups, 3);
+        assert_eq!(summary.completed, 2);
+        assert_eq!(summary.waiting_mean(), 0.5);
+        assert_eq!(summary.mean_setup_nodes(), 1.5 / 10.0);
+    }
+
+    #[test]
+    fn test_setup_capacity() {
+        // Requests arrive every 2 ticks, the one at 2 wakes the first node
+        // and the one at 4 the second node, both wait in place of the nodes.
+        // The one at 6 takes the place in the queue, if there is one.
+        let setup_system = |queue_capacity: QueueCapacity| {
+            System::new(
+                2,
+                queue_capacity,
+                ConsumingDistribution::Degenerate { μ: 1.0 },
+                ProducingDistribution::Degenerate { value: 2 },
+                0,
+            )
+            .with_setup(ConsumingDistribution::Degenerate { μ: 0.2 })
+        };
+
+        let summary = setup_system(QueueCapacity::Finite(1)).run_until(6.5);
+        assert_eq!(summary.setups, 2);
+        assert_eq!(summary.accepted, 3);
+        assert_eq!(summary.lost, 0);
+
+        let summary = setup_system(QueueCapacity::Loss).run_until(6.5);
+        assert_eq!(summary.setups, 2);
+        assert_eq!(summary.accepted, 2);
+        assert_eq!(summary.lost, 1);
+        assert_eq!(summary.max_in_system, Some(2));
+        assert_eq!(summary.blocking_probability(), 2.5 / 6.5);
+    }
+
+    fn breakdown_system(interruption: Interruption) -> System {
+        System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 0.5 },
+            ProducingDistribution::Degenerate { value: 4 },
+            0,
+        )
+        .with_breakdowns(
+            Breakdowns::new(
+                ConsumingDistribution::Degenerate { μ: 1.0 / 4.5 },
+                ConsumingDistribution::Degenerate { μ: 2.0 },
+            )
+            .with_interruption(interruption),
+        )
+    }
+
+    #[test]
+    fn test_breakdowns() {
+        // The node fails at 4.5 with 1.5 ticks of service left for the
+        // request arrived at 4, and is repaired at 5.
+        let summary = breakdown_system(Interruption::Resume).run_until(9.0);
+        assert_eq!(summary.failures, 1);
+        assert_eq!(summary.interrupted, 1);
+        assert_eq!(summary.sojourn_mean(), 2.5);
+        assert_eq!(summary.failure_delay_mean(), 0.5);
+        assert_eq!(summary.availability(), 1.0 - 0.5 / 9.0);
+
+        let summary = breakdown_system(Interruption::Requeue).run_until(9.0);
+        assert_eq!(summary.completed, 1);
+        assert_eq!(summary.sojourn_mean(), 3.0);
+        assert_eq!(summary.failure_delay_mean(), 0.5);
+
+        let mut system = breakdown_system(Interruption::Lose);
+        let events = system.by_ref().take(3).collect::<Vec<_>>();
+        assert_eq!(events[1].r#type, EventType::Failure);
+        assert_eq!(events[1].node, Some(0));
+        assert_eq!(events[1].down_nodes, 1);
+        assert_eq!(events[1].requests_in_system(), 0);
+
+        let summary = system.run_until(9.0);
+        assert_eq!(summary.completed, 0);
+        assert_eq!(summary.interrupted_lost, 1);
+    }
+
+    fn servers_system(assignment: Assignment) -> System {
+        System::new(
+            2,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 1.0 / 3.0 },
+            ProducingDistribution::Degenerate { value: 2 },
+            0,
+        )
+        .with_servers([Server::new(), Server::new().with_speed(2.0)])
+        .with_assignment(assignment)
+    }
+
+    #[test]
+    fn test_requeue_capacity() {
+        // The node fails at 2.5 while the request arrived at 2 takes the
+        // only place in the queue, so the interrupted request is lost.
+        let mut system = System::new(
+            1,
+            QueueCapacity::Finite(1),
+            ConsumingDistribution::Degenerate { μ: 0.5 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        )
+        .with_breakdowns(Breakdowns::new(
+            ConsumingDistribution::Degenerate { μ: 0.4 },
+            ConsumingDistribution::Degenerate { μ: 4.0 },
+        ));
+
+        let summary = system.run_until(3.5);
+        assert_eq!(summary.failures, 1);
+        assert_eq!(summary.interrupted, 1);
+        assert_eq!(summary.interrupted_lost, 1);
+        assert_eq!(summary.accepted, 3);
+        assert_eq!(summary.lost, 0);
+        assert_eq!(summary.mean_queue_length(), 1.25 / 3.5);
+    }
+
+    #[test]
+    fn test_servers() {
+        // The fast node serves every request before the next one arrives.
+        let summary = servers_system(Assignment::FastestFirst).run_until(9.75);
+        assert_eq!(summary.nodes[0].completed, 0);
+        assert_eq!(summary.nodes[1].completed, 4);
+        assert_eq!(summary.nodes[1].utilization(), 6.0 / 9.75);
+        assert_eq!(summary.sojourn_mean(), 1.5);
+
+        // Requests alternate between the nodes.
+        let summary = servers_system(Assignment::RoundRobin).run_until(9.75);
+        assert_eq!(summary.nodes[0].completed, 2);
+        assert_eq!(summary.nodes[0].utilization(), 6.0 / 9.75);
+        assert_eq!(summary.nodes[1].completed, 2);
+        assert_eq!(summary.nodes[1].throughput(), 2.0 / 9.75);
+        assert_eq!(summary.sojourn_mean(), 2.25);
+    }
+
+    #[test]
+    fn test_busy_periods() {
+        // Node 0 serves at [2, 5] and [6, 9], node 1 at [4, 5.5] and
+        // [8, 9.5], and the system is empty in between.
+        let summary = servers_system(Assignment::RoundRobin).run_until(9.75);
+        assert_eq!(summary.utilization(), 9.0 / 9.75 / 2.0);
+        assert_eq!(summary.busy_periods.count, 2);
+        assert_eq!(summary.busy_periods.mean(), 3.5);
+        assert_eq!(summary.busy_periods.length_max, 3.5);
+        assert_eq!(summary.nodes[0].busy_periods.count, 2);
+        assert_eq!(summary.nodes[0].busy_periods.mean(), 3.0);
+        assert_eq!(summary.nodes[1].busy_periods.mean(), 1.5);
+
+        // The ongoing busy period is not counted.
+        let summary = servers_system(Assignment::RoundRobin).run_until(8.5);
+        assert_eq!(summary.busy_periods.count, 1);
+        assert_eq!(summary.busy_periods.current, 2.5);
+        assert_eq!(summary.nodes[1].busy_periods.current, 0.5);
+    }
+
+    #[test]
+    fn test_buckets() {
+        let summary = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 1.0 },
+            ProducingDistribution::Degenerate { value: 3 },
+            0,
+        )
+        .with_buckets(5.0)
+        .run_until(12.0);
+
+        let buckets = &summary.buckets;
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[1].start, 5.0);
+        assert_eq!(buckets[1].offered, 2);
+        assert_eq!(buckets[1].completed, 2);
+        assert_eq!(buckets[1].sojourn_mean(), 1.0);
+        assert_eq!(buckets[1].mean_busy_nodes(), 2.0 / 5.0);
+        assert_eq!(buckets[2].duration, 2.0);
+        assert_eq!(buckets[2].arrival_rate(), 1.0 / 2.0);
+        assert_eq!(buckets[2].completed, 0);
+        assert_eq!(summary.classes[0].buckets, summary.buckets);
+    }
+
+    #[test]
+    fn test_renewal_arrivals() {
+        // Requests arrive every 2.5 ticks and are served for 3 ticks.
+        let summary = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 1.0 / 3.0 },
+            ProducingDistribution::Renewal {
+                interval: ConsumingDistribution::Degenerate { μ: 0.4 },
+            },
+            0,
+        )
+        .run_until(9.0);
+
+        assert_eq!(summary.offered, 3);
+        assert_eq!(summary.completed, 2);
+        assert_eq!(summary.waiting_mean(), 0.25);
+    }
+
+    #[test]
+    fn test_modulated_arrivals() {
+        let mut system = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Exponential { λ: 1.0 },
+            ProducingDistribution::Modulated {
+                process: Mmpp::on_off(2.0, 1.0, 3.0),
+            },
+            1,
+        );
+        assert!(system
+            .by_ref()
+            .take(100)
+            .any(|event| event.r#type == EventType::PhaseChange));
+
+        let summary = system.run_until(20_000.0);
+        let class = &summary.classes[0];
+        let probabilities = class.phase_probabilities();
+        let rates = class.phase_rates();
+
+        assert!((probabilities[0] - 0.25).abs() < 0.02);
+        assert!((rates[0] - 2.0).abs() < 0.1);
+        assert_eq!(rates[1], 0.0);
+        assert!((summary.offered as f64 / summary.time - 0.5).abs() < 0.05);
+    }
+
+    /// Service time is 1 or 3 with equal probability.
+    #[derive(Debug)]
+    struct TwoPoint;
+
+    impl TimeDistribution for TwoPoint {
+        fn sample(&self, rng: &mut dyn RngCore) -> f64 {
+            if rng.gen_bool(0.5) {
+                1.0
+            } else {
+                3.0
+            }
+        }
+
+        fn mean(&self) -> f64 {
+            2.0
+        }
+
+        fn variance(&self) -> f64 {
+            1.0
+        }
+
+        fn description(&self) -> String {
+            "TwoPoint".to_string()
+        }
+    }
+
+    #[test]
+    fn test_custom_distribution() {
+        let mut system = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::custom(TwoPoint),
+            ProducingDistribution::Renewal {
+                interval: ConsumingDistribution::custom(TwoPoint),
+            },
+            0,
+        );
+        let times = system
+            .by_ref()
+            .take(50)
+            .filter_map(|event| event.request)
+            .map(|request| request.time_to_finish)
+            .collect::<Vec<_>>();
+        assert!(times.contains(&1.0) && times.contains(&3.0));
+        assert!(times.iter().all(|time| *time == 1.0 || *time == 3.0));
+
+        let summary = system.run_until(10_000.0);
+        assert!((summary.offered as f64 / summary.time - 0.5).abs() < 0.05);
+    }
+
+    /// Nothing arrives till the phase changes at 2, then requests arrive
+    /// every unit of time.
+    #[derive(Debug)]
+    struct Delayed;
+
+    impl ArrivalProcess for Delayed {
+        fn sample(&self, _time: f64, phase: usize, _rng: &mut dyn RngCore) -> NextArrival {
+            match phase {
+                0 => NextArrival::PhaseChange(2.0),
+                _ => NextArrival::Arrival(1.0),
+            }
+        }
+
+        fn phases(&self) -> usize {
+            2
+        }
+
+        fn jump(&self, _phase: usize, _rng: &mut dyn RngCore) -> usize {
+            1
+        }
+
+        fn mean(&self) -> f64 {
+            1.0
+        }
+
+        fn variance(&self) -> Option<f64> {
+            None
+        }
+
+        fn description(&self) -> String {
+            "Delayed".to_string()
+        }
+    }
+
+    #[test]
+    fn test_custom_arrival_process() {
+        let summary = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 2.0 },
+            Delayed,
+            0,
+        )
+        .run_until(5.5);
+
+        assert_eq!(summary.offered, 3);
+        assert_eq!(
+            summary.classes[0].phase_probabilities(),
+            vec![2.0 / 5.5, 3.5 / 5.5]
+        );
+        assert_eq!(summary.classes[0].phase_rates(), vec![0.0, 3.0 / 3.5]);
+    }
+
+    fn population_system(sources: usize) -> System {
+        System::from_class(
+            1,
+            QueueCapacity::Unbounded,
+            CustomerClass::new(
+                ConsumingDistribution::Degenerate { μ: 1.0 },
+                ProducingDistribution::Degenerate { value: 2 },
+            )
+            .with_population(sources),
+            0,
+        )
+    }
+
+    #[test]
+    fn test_finite_population() {
+        // The only source sends requests at 2, 5 and 8.
+        let summary = population_system(1).run_until(9.5);
+        assert_eq!(summary.offered, 3);
+        assert_eq!(summary.completed, 3);
+        assert_eq!(summary.sojourn_mean(), 1.0);
+
+        // Both sources send requests at 2, then they are out of phase.
+        let summary = population_system(2).run_until(9.5);
+        assert_eq!(summary.offered, 6);
+        assert_eq!(summary.completed, 5);
+        assert_eq!(summary.sojourn_mean(), 1.2);
+        assert_eq!(summary.throughput(), 5.0 / 9.5);
+    }
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+        calls: Vec<(&'static str, f64, u64)>,
+    }
+
+    impl Observer for Recorder {
+        fn on_arrival(&mut self, time: f64, request: &Request) {
+            self.calls.push(("arrival", time, request.id));
+        }
+
+        fn on_block(&mut self, time: f64, request: &Request) {
+            self.calls.push(("block", time, request.id));
+        }
+
+        fn on_service_start(&mut self, time: f64, request: &Request) {
+            self.calls.push(("service_start", time, request.id));
+        }
+
+        fn on_departure(&mut self, time: f64, request: &Request) {
+            self.calls.push(("departure", time, request.id));
+        }
+    }
+
+    #[test]
+    fn test_without_arrivals() {
+        let mut system = System::new(
+            1,
+            QueueCapacity::Unbounded,
+            ConsumingDistribution::Degenerate { μ: 0.5 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        )
+        .without_arrivals();
+        assert_eq!(system.next(), None);
+
+        let request = Request {
+            entered_at: Some(1.0),
+            ..Request::new(0.0)
+        };
+        system.receive(1.0, &request);
+        let events = system.by_ref().collect::<Vec<_>>();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].r#type, EventType::Departure);
+        assert_eq!(events[0].time, 3.0);
+
+        let summary = system.run_for_events(5);
+        assert_eq!(summary.events, 2);
+        assert_eq!(summary.completed, 1);
+    }
+
+    #[test]
+    fn test_observers() {
+        let recorder = Rc::new(RefCell::new(Recorder::default()));
+        let second = Rc::new(RefCell::new(Recorder::default()));
+
+        let mut system = System::new(
+            1,
+            QueueCapacity::Loss,
+            ConsumingDistribution::Degenerate { μ: 0.5 },
+            ProducingDistribution::Degenerate { value: 1 },
+            0,
+        )
+        .with_observer(recorder.clone());
+        system.add_observer(second.clone());
+
+        system.run_until(3.0);
+
+        let expected = vec![
+            ("arrival", 1.0, 0),
+            ("service_start", 1.0, 0),
+            ("arrival", 2.0, 1),
+            ("block", 2.0, 1),
+            ("departure", 3.0, 0),
+            ("arrival", 3.0, 2),
+            ("service_start", 3.0, 2),
+        ];
+        assert_eq!(recorder.borrow().calls, expected);
+        assert_eq!(second.borrow().calls, expected);
+    }
+
+    #[test]
+    fn test_custom_rng() {
+        let first = trajectory(&mut system::<ChaCha8Rng>(7));
+        let second = trajectory(&mut system::<ChaCha8Rng>(7));
+
+        assert_eq!(first, second);
     }
 }
 